@@ -1,4 +1,14 @@
-use std::env;
+use std::{env, fs, path::Path};
+
+/// Seconds the loader waits for a host before booting the fallback image, 0 to wait forever
+const DEFAULT_AUTOBOOT_SECS: &str = "5";
+
+/// Image booted when no host answers: `cached`, `embedded` or `none`
+const DEFAULT_AUTOBOOT_FALLBACK: &str = "cached";
+
+/// Core that runs the loader, the others park. Both boards have 4.
+const DEFAULT_BOOT_CORE: &str = "0";
+const NUM_CORES: u64 = 4;
 
 fn main() {
     // because this script is called from the Makefile, the
@@ -10,4 +20,99 @@ fn main() {
     // either the linker script has changed or the build script itself
     println!("cargo:rerun-if-changed={}", linker_file);
     println!("cargo:rerun-if-changed=build.rs");
-}
\ No newline at end of file
+
+    set_cfgs();
+    write_config();
+    locate_embedded_image();
+}
+
+/// Derive the cfgs the features can't express themselves, and reject the feature combinations
+/// that make no sense:
+/// - `pl011`: a PL011 is driven, for the console (unless the mini UART has it) or as the
+///   dedicated loader link
+fn set_cfgs() {
+    println!("cargo:rustc-check-cfg=cfg(pl011)");
+
+    let mini_uart_console = env::var_os("CARGO_FEATURE_CONSOLE_MINI_UART").is_some();
+    let extra_loader_uart = env::var_os("CARGO_FEATURE_EXTRA_LOADER_UART").is_some();
+
+    let pl011 = !mini_uart_console || extra_loader_uart;
+    if pl011 {
+        println!("cargo:rustc-cfg=pl011");
+    }
+
+    if env::var_os("CARGO_FEATURE_PL011_RX_DMA").is_some() && !pl011 {
+        panic!("pl011_rx_dma needs a PL011 loader link: the PL011 console, or a loader_uartN");
+    }
+}
+
+/// Turn the build-time settings (environment variables, see the Makefile) into `config.rs`,
+/// which src/config.rs includes. Invalid values fail the build rather than the boot.
+fn write_config() {
+    println!("cargo:rerun-if-env-changed=AUTOBOOT_SECS");
+    println!("cargo:rerun-if-env-changed=AUTOBOOT_FALLBACK");
+    println!("cargo:rerun-if-env-changed=BOOT_CORE");
+
+    let autoboot_secs = env::var("AUTOBOOT_SECS").unwrap_or_else(|_| DEFAULT_AUTOBOOT_SECS.into());
+    let autoboot_secs: u64 = match autoboot_secs.trim().parse() {
+        Ok(secs) => secs,
+        Err(_) => panic!("AUTOBOOT_SECS must be a number of seconds, got '{}'", autoboot_secs),
+    };
+
+    let autoboot_fallback =
+        env::var("AUTOBOOT_FALLBACK").unwrap_or_else(|_| DEFAULT_AUTOBOOT_FALLBACK.into());
+    let autoboot_fallback = match autoboot_fallback.trim() {
+        "cached" => "Some(crate::loader::cached_image)",
+        "embedded" if env::var_os("CARGO_FEATURE_EMBEDDED_IMAGE").is_some() => {
+            "Some(crate::loader::embedded_image)"
+        }
+        "embedded" => panic!("AUTOBOOT_FALLBACK=embedded needs an embedded image (EMBEDDED_IMAGE)"),
+        "none" => "None",
+        other => panic!(
+            "AUTOBOOT_FALLBACK must be 'cached', 'embedded' or 'none', got '{}'",
+            other
+        ),
+    };
+
+    let boot_core = env::var("BOOT_CORE").unwrap_or_else(|_| DEFAULT_BOOT_CORE.into());
+    let boot_core: u64 = match boot_core.trim().parse() {
+        Ok(core) if core < NUM_CORES => core,
+        _ => panic!("BOOT_CORE must be a core id below {}, got '{}'", NUM_CORES, boot_core),
+    };
+
+    let config = format!(
+        "/// Seconds to wait for a host before booting the fallback image, 0 to wait forever\n\
+         pub const AUTOBOOT_SECS: u64 = {};\n\
+         \n\
+         /// Finds the image booted when no host answers in time, None to keep waiting\n\
+         pub const AUTOBOOT_FALLBACK: Option<fn() -> Result<crate::loader::Image, &'static str>> = {};\n\
+         \n\
+         /// Core that runs the loader\n\
+         pub const BOOT_CORE: u64 = {};\n",
+        autoboot_secs, autoboot_fallback, boot_core
+    );
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("config.rs"), config).unwrap();
+}
+
+/// With the `embedded_image` feature, hand the path of the image to embed (`EMBEDDED_IMAGE`,
+/// relative to the crate root) to the loader's `include_bytes!`.
+fn locate_embedded_image() {
+    println!("cargo:rerun-if-env-changed=EMBEDDED_IMAGE");
+
+    if env::var_os("CARGO_FEATURE_EMBEDDED_IMAGE").is_none() {
+        return;
+    }
+
+    let image = env::var("EMBEDDED_IMAGE")
+        .expect("the embedded_image feature needs EMBEDDED_IMAGE=<path to the image>");
+    let path = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join(image.trim());
+
+    if !path.is_file() {
+        panic!("embedded image {} not found", path.display());
+    }
+
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!("cargo:rustc-env=EMBEDDED_IMAGE_PATH={}", path.display());
+}
@@ -14,22 +14,22 @@
 //!
 //! crate::cpu::arch_cpu
 
-use cortex_a::asm;
+use cortex_a::{asm, asm::barrier, registers::MPIDR_EL1};
+use tock_registers::interfaces::Readable;
 
 pub use asm::nop; // export cpu::nop() for waiting
 
-/// Pause execution on the core.
+/// Wake the cores waiting for an event (`wfe`), once everything written so far is visible to them
 #[inline(always)]
-pub fn wait_forever() -> ! {
-    loop{
-        asm::wfe();
-    }
+pub fn wake_waiting_cores() {
+    barrier::dsb(barrier::SY);
+    asm::sev();
 }
 
-#[cfg(feature = "bsp_rpi3")]
+/// Return the executing core's id (Aff0 of MPIDR_EL1, the RPi's cores are 0-3)
 #[inline(always)]
-pub fn spin_for_cycles(cycles: usize) {
-    for _ in 0..cycles {
-        asm::nop();
-    }
-}
\ No newline at end of file
+pub fn core_id() -> usize {
+    const CORE_ID_MASK: u64 = 0b11;
+
+    (MPIDR_EL1.get() & CORE_ID_MASK) as usize
+}
@@ -13,8 +13,10 @@ use crate::kernel_init;
 core::arch::global_asm!(include_str!("boot.s"));
 
 
+/// The Rust entry point, called by boot.s with the device tree address the firmware passed
+/// (0 if there is none)
 #[no_mangle]
-pub unsafe fn _start_rust() {
-    kernel_init()
+pub unsafe extern "C" fn _start_rust(boot_dtb_addr: usize) {
+    kernel_init(boot_dtb_addr)
 }
 
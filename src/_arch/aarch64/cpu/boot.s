@@ -41,21 +41,63 @@ https://sourceware.org/binutils/docs-2.36/as/AArch64_002dRelocations.html
 
 .equ _core_id_mask, 0b11
 
+// Wait for a release address in the spin table slot at x2, then jump to it.
+// x0-x3 are zero for the image, as the arm64 boot protocol asks.
+.macro PARK_ON_SPIN_TABLE
+1:
+	wfe
+	ldr	x3, [x2]
+	cbz	x3, 1b
+	mov	x0, xzr
+	mov	x1, xzr
+	mov	x2, xzr
+	mov	x4, x3
+	mov	x3, xzr
+	br	x4
+.endm
+
 .section .text._start
 
 // fn _start() -> do initialization work and call rust code
 _start:
-    // We have 4 cores. Only proceed with the boot core, core0.
+    // We have 4 cores. Only proceed with the boot core, BOOT_CORE_ID (picked at build time).
     // move MPIDR_EL1 register content to general purpose register x1   
     mrs x1, MPIDR_EL1
     // mask the mpidr_el1 register to check what core is currently executing
     and x1, x1, _core_id_mask
-    // load the boot core id (0) to x2
+    // load the boot core id to x2
     ldr x2, BOOT_CORE_ID
     cmp x1, x2 // compare the boot id 
-    b.ne _park_core
+    b.eq _boot_core
+
+    // The firmware starts core 0 alone, the others wait in its armstub for a release address in
+    // their spin table slot. Core 0 starts the boot core here, passes the device tree on to it,
+    // and parks.
+    cbnz x1, _park_core
+    adr x3, _boot_dtb_addr
+    str x0, [x3]
+    ldr x3, SPIN_TABLE_START
+    adr x4, _start
+    dmb sy // the device tree address is in RAM before the boot core can see its release
+    str x4, [x3, x2, lsl #3]
+    dsb sy
+    sev
+    b _park_core
 
+_boot_core:
     // the core executing these lines is the boot core
+    // the firmware passes the address of the device tree in x0, keep it for the image. Core 0
+    // passes it on when it released us, the armstub zeroed x0 then.
+    mov x19, x0
+    cbnz x19, 1f
+    ldr x19, _boot_dtb_addr
+1:
+
+    // secondary cores may be polling their slots already, start them off empty
+    ldr x0, SPIN_TABLE_START
+    stp xzr, xzr, [x0]
+    stp xzr, xzr, [x0, #16]
+
     ADR_ABS x0, __bss_start
 	ADR_ABS x1, __bss_end_exclusive
 
@@ -70,23 +112,80 @@ _relocate_bootloader:
 	ADR_ABS x1, __binary_start // Where the binary was linked (ex. 0x2000000)
 	ADR_ABS x2, __binary_end_exclusive // End of the binary.
 
+// The MMU is off, so every access goes uncached to RAM: move 64 bytes per iteration to keep
+// their number down. The binary's start and end are 64 byte aligned (see link.ld).
 _copy_loop:
-	ldr x3, [x0], #8 //  load to x3 whatever is in x0, advance x0 by 8
-	str x3, [x1], #8 //  store whatever is in x3 in the address of x3, advance by 8 x1
+	ldp x3, x4, [x0], #16 // load 16 bytes from x0, advance x0
+	ldp x5, x6, [x0], #16
+	ldp x7, x8, [x0], #16
+	ldp x9, x10, [x0], #16
+	stp x3, x4, [x1], #16 // store them at x1, advance x1
+	stp x5, x6, [x1], #16
+	stp x7, x8, [x1], #16
+	stp x9, x10, [x1], #16
 	cmp x1, x2
 	b.lo _copy_loop
 
-// setting up stack:
-ADR_ABS x0, __boot_core_stack_end_exclusive
+// The secondary cores wait in the copy at the load address, which the image will overwrite.
+// Release them into the relocated copy of the park loop.
+	ldr x0, SPIN_TABLE_START
+	ADR_ABS x1, _park_core_relocated
+	ldr x3, BOOT_CORE_ID
+	mov x2, xzr
+_release_secondary_cores:
+	cmp x2, x3
+	b.eq 1f // not the boot core's slot
+	str x1, [x0, x2, lsl #3]
+1:
+	add x2, x2, #1
+	cmp x2, #4
+	b.lo _release_secondary_cores
+	dsb sy
+	sev
+
+// setting up stacks, in the boot core's slot of the core stacks (see link.ld):
+// __core_stacks_start + id * slot size
+ldr x1, BOOT_CORE_ID
+ADR_ABS x2, __core_stack_slot_size
+ADR_ABS x0, __core_stacks_start
+madd x0, x1, x2, x0
+// SP_EL0 for the synchronous exception handlers (see exception.s), at the top of the bottom stack
+ADR_ABS x1, __core_exception_stack_size
+add x1, x0, x1
+msr SP_EL0, x1
+// and the core's own at the top of the slot, above the guard page
+add x0, x0, x2
 mov sp, x0
-// let's begin!
-ADR_ABS x0, _start_rust
-br x0
+// let's begin! with the device tree address as argument
+mov x0, x19
+ADR_ABS x1, _start_rust
+br x1
 
+// Secondary cores (x1 is the core id) wait on their spin table slot
 _park_core:
-    wfe // wait for event
-    b _park_core // jump to loop if event occured.
+    ldr x2, SPIN_TABLE_START
+    add x2, x2, x1, lsl #3
+    PARK_ON_SPIN_TABLE
+
+// The device tree address, when core 0 passes it on to another boot core
+.balign 8
+_boot_dtb_addr:
+    .quad 0
 
 .size _start, . - _start // tells the linker the size of _start, doesn't look important
 .type _start, function // start is a function
 .global _start // _start is an external symbol ready to link
+
+// Where the secondary cores wait for the image, in a page of its own (see link.ld) that the device
+// tree handed to the image reserves. They come from the firmware's armstub or from _park_core,
+// both zero x0-x3, so the slot is looked up again.
+.section .text._park_core
+_park_core_relocated:
+    mrs x1, MPIDR_EL1
+    and x1, x1, _core_id_mask
+    ldr x2, SPIN_TABLE_START
+    add x2, x2, x1, lsl #3
+    // The slot still holds this address, wait for the image to write another one. The cleared
+    // slot also tells the loader the core arrived (see cpu::smp).
+    str xzr, [x2]
+    PARK_ON_SPIN_TABLE
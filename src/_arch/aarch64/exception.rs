@@ -0,0 +1,468 @@
+/*
+ * File: exception.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 2:31:09 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Architectural synchronous and asynchronous exception handling.
+//!
+//! The loader runs at EL2, so its vectors go into VBAR_EL2 and the syndrome comes from the EL2
+//! registers. Synchronous exceptions, FIQs, SErrors and anything arriving through a vector the
+//! loader doesn't use are fatal: the saved context is decoded and printed through the panic
+//! console, and the core halts.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::exception::arch_exception
+
+use crate::{cpu, exception};
+use core::{arch::asm, cell::UnsafeCell, fmt};
+use tock_registers::{interfaces::Readable, register_bitfields, registers::InMemoryRegister};
+
+// Assembly counterpart to this file.
+core::arch::global_asm!(include_str!("exception.s"));
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u64,
+
+    /// Exception Syndrome Register (EL2)
+    ESR [
+        /// Exception Class, why the exception was taken
+        EC OFFSET(26) NUMBITS(6) [],
+        /// Instruction Length: 32 bit instruction if set
+        IL OFFSET(25) NUMBITS(1) [],
+        /// Instruction Specific Syndrome, meaning depends on EC
+        ISS OFFSET(0) NUMBITS(25) []
+    ],
+
+    /// Saved Program Status Register (EL2)
+    SPSR [
+        N OFFSET(31) NUMBITS(1) [],
+        Z OFFSET(30) NUMBITS(1) [],
+        C OFFSET(29) NUMBITS(1) [],
+        V OFFSET(28) NUMBITS(1) [],
+        /// Illegal Execution State
+        IL OFFSET(20) NUMBITS(1) [],
+        D OFFSET(9) NUMBITS(1) [],
+        A OFFSET(8) NUMBITS(1) [],
+        I OFFSET(7) NUMBITS(1) [],
+        F OFFSET(6) NUMBITS(1) [],
+        /// Exception level and stack pointer the exception was taken from
+        M OFFSET(0) NUMBITS(4) [
+            EL0t = 0b0000,
+            EL1t = 0b0100,
+            EL1h = 0b0101,
+            EL2t = 0b1000,
+            EL2h = 0b1001
+        ]
+    ]
+}
+
+/// Exception classes (ESR.EC) with a meaningful data or instruction abort ISS
+mod ec {
+    pub const INSTRUCTION_ABORT_LOWER_EL: u64 = 0b10_0000;
+    pub const INSTRUCTION_ABORT_CURRENT_EL: u64 = 0b10_0001;
+    pub const DATA_ABORT_LOWER_EL: u64 = 0b10_0100;
+    pub const DATA_ABORT_CURRENT_EL: u64 = 0b10_0101;
+}
+
+/// HCR_EL2 bits routing physical FIQs (FMO), IRQs (IMO) and SErrors (AMO) to EL2
+const HCR_EL2_FMO_IMO_AMO: u64 = 0b111 << 3;
+
+/// Wrapper structs for memory copies of registers.
+#[repr(transparent)]
+struct SpsrEL2(InMemoryRegister<u64, SPSR::Register>);
+#[repr(transparent)]
+struct EsrEL2(InMemoryRegister<u64, ESR::Register>);
+
+/// The exception context as it is stored on the stack on exception entry, see exception.s.
+#[repr(C)]
+struct ExceptionContext {
+    /// General Purpose Registers.
+    gpr: [u64; 30],
+
+    /// The link register, aka x30.
+    lr: u64,
+
+    /// Exception link register. The program counter at the time the exception happened.
+    elr_el2: u64,
+
+    /// Saved program status.
+    spsr_el2: SpsrEL2,
+
+    /// Exception syndrome register.
+    esr_el2: EsrEL2,
+
+    /// Fault address register. Only valid for aborts, see `EsrEL2::far_valid()`.
+    far_el2: u64,
+
+    /// The stack pointer at the time the exception happened
+    sp: u64,
+}
+
+// `_context_size` in exception.s
+const _: () = assert!(core::mem::size_of::<ExceptionContext>() == 16 * 18);
+
+// Symbols from exception.s
+extern "Rust" {
+    static __exception_vector_start: UnsafeCell<()>;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Prints verbose information about the exception and then panics.
+fn default_exception_handler(vector: &'static str, exc: &ExceptionContext) -> ! {
+    let fault_addr = exc.esr_el2.far_valid().then_some(exc.far_el2 as usize);
+    if let Some(core) = cpu::stack::overflowed_core(fault_addr) {
+        panic!("CPU exception: {}, stack overflow on core {}\n\n{}", vector, core, exc);
+    }
+
+    panic!("CPU exception: {}\n\n{}", vector, exc);
+}
+
+//------------------------------------------------------------------------------
+// Current, EL0
+//------------------------------------------------------------------------------
+
+#[no_mangle]
+extern "C" fn current_el0_synchronous(e: &mut ExceptionContext) {
+    default_exception_handler("synchronous, current EL with SP0", e);
+}
+
+#[no_mangle]
+extern "C" fn current_el0_irq(e: &mut ExceptionContext) {
+    default_exception_handler("IRQ, current EL with SP0", e);
+}
+
+#[no_mangle]
+extern "C" fn current_el0_fiq(e: &mut ExceptionContext) {
+    default_exception_handler("FIQ, current EL with SP0", e);
+}
+
+#[no_mangle]
+extern "C" fn current_el0_serror(e: &mut ExceptionContext) {
+    default_exception_handler("SError, current EL with SP0", e);
+}
+
+//------------------------------------------------------------------------------
+// Current, ELx
+//------------------------------------------------------------------------------
+
+#[no_mangle]
+extern "C" fn current_elx_synchronous(e: &mut ExceptionContext) {
+    default_exception_handler("synchronous, current EL", e);
+}
+
+#[no_mangle]
+extern "C" fn current_elx_irq(_e: &mut ExceptionContext) {
+    use exception::asynchronous::interface::IRQManager;
+
+    // an overflow that hasn't reached the guard page yet
+    cpu::stack::check();
+
+    let token = unsafe { &exception::asynchronous::IRQContext::new() };
+    exception::asynchronous::irq_manager().handle_pending_irqs(token);
+}
+
+#[no_mangle]
+extern "C" fn current_elx_fiq(e: &mut ExceptionContext) {
+    default_exception_handler("FIQ, current EL", e);
+}
+
+#[no_mangle]
+extern "C" fn current_elx_serror(e: &mut ExceptionContext) {
+    default_exception_handler("SError, current EL", e);
+}
+
+//------------------------------------------------------------------------------
+// Lower, AArch64
+//------------------------------------------------------------------------------
+
+#[no_mangle]
+extern "C" fn lower_aarch64_synchronous(e: &mut ExceptionContext) {
+    default_exception_handler("synchronous, lower EL (AArch64)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch64_irq(e: &mut ExceptionContext) {
+    default_exception_handler("IRQ, lower EL (AArch64)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch64_fiq(e: &mut ExceptionContext) {
+    default_exception_handler("FIQ, lower EL (AArch64)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch64_serror(e: &mut ExceptionContext) {
+    default_exception_handler("SError, lower EL (AArch64)", e);
+}
+
+//------------------------------------------------------------------------------
+// Lower, AArch32
+//------------------------------------------------------------------------------
+
+#[no_mangle]
+extern "C" fn lower_aarch32_synchronous(e: &mut ExceptionContext) {
+    default_exception_handler("synchronous, lower EL (AArch32)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch32_irq(e: &mut ExceptionContext) {
+    default_exception_handler("IRQ, lower EL (AArch32)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch32_fiq(e: &mut ExceptionContext) {
+    default_exception_handler("FIQ, lower EL (AArch32)", e);
+}
+
+#[no_mangle]
+extern "C" fn lower_aarch32_serror(e: &mut ExceptionContext) {
+    default_exception_handler("SError, lower EL (AArch32)", e);
+}
+
+//------------------------------------------------------------------------------
+// Misc
+//------------------------------------------------------------------------------
+
+/// Human readable SPSR_EL2.
+#[rustfmt::skip]
+impl fmt::Display for SpsrEL2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Raw value.
+        writeln!(f, "SPSR_EL2: {:#010x}", self.0.get())?;
+
+        let to_flag_str = |x| -> _ {
+            if x { "Set" } else { "Not set" }
+        };
+
+        writeln!(f, "      Flags:")?;
+        writeln!(f, "            Negative (N): {}", to_flag_str(self.0.is_set(SPSR::N)))?;
+        writeln!(f, "            Zero     (Z): {}", to_flag_str(self.0.is_set(SPSR::Z)))?;
+        writeln!(f, "            Carry    (C): {}", to_flag_str(self.0.is_set(SPSR::C)))?;
+        writeln!(f, "            Overflow (V): {}", to_flag_str(self.0.is_set(SPSR::V)))?;
+
+        let to_mask_str = |x| -> _ {
+            if x { "Masked" } else { "Unmasked" }
+        };
+
+        writeln!(f, "      Exception handling state:")?;
+        writeln!(f, "            Debug  (D): {}", to_mask_str(self.0.is_set(SPSR::D)))?;
+        writeln!(f, "            SError (A): {}", to_mask_str(self.0.is_set(SPSR::A)))?;
+        writeln!(f, "            IRQ    (I): {}", to_mask_str(self.0.is_set(SPSR::I)))?;
+        writeln!(f, "            FIQ    (F): {}", to_mask_str(self.0.is_set(SPSR::F)))?;
+
+        writeln!(f, "      Illegal Execution State (IL): {}",
+            to_flag_str(self.0.is_set(SPSR::IL))
+        )?;
+
+        let mode = match self.0.read_as_enum(SPSR::M) {
+            Some(SPSR::M::Value::EL0t) => "EL0t",
+            Some(SPSR::M::Value::EL1t) => "EL1t",
+            Some(SPSR::M::Value::EL1h) => "EL1h",
+            Some(SPSR::M::Value::EL2t) => "EL2t",
+            Some(SPSR::M::Value::EL2h) => "EL2h",
+            None => "Unknown",
+        };
+        write!(f, "      Taken from: {}", mode)
+    }
+}
+
+impl EsrEL2 {
+    #[inline(always)]
+    fn exception_class(&self) -> u64 {
+        self.0.read(ESR::EC)
+    }
+
+    #[inline(always)]
+    fn is_abort(&self) -> bool {
+        matches!(
+            self.exception_class(),
+            ec::INSTRUCTION_ABORT_LOWER_EL
+                | ec::INSTRUCTION_ABORT_CURRENT_EL
+                | ec::DATA_ABORT_LOWER_EL
+                | ec::DATA_ABORT_CURRENT_EL
+        )
+    }
+
+    /// FAR_EL2 only holds the faulting address for aborts (and watchpoints, which the loader
+    /// doesn't use), and then only if ISS.FnV is clear.
+    #[inline(always)]
+    fn far_valid(&self) -> bool {
+        const ISS_FNV: u64 = 1 << 10;
+
+        self.is_abort() && self.0.read(ESR::ISS) & ISS_FNV == 0
+    }
+}
+
+/// Human readable ESR_EL2.
+#[rustfmt::skip]
+impl fmt::Display for EsrEL2 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Raw print of whole register.
+        writeln!(f, "ESR_EL2: {:#010x}", self.0.get())?;
+
+        // Raw print of exception class.
+        write!(f, "      Exception Class         (EC) : {:#x}", self.exception_class())?;
+
+        // Exception class.
+        let ec_translation = match self.exception_class() {
+            0b00_0000 => "Unknown reason (e.g. undefined instruction)",
+            0b00_0001 => "Trapped WFI/WFE",
+            0b00_0111 => "Trapped SIMD/FP access",
+            0b00_1110 => "Illegal Execution State",
+            0b01_0110 => "HVC from AArch64",
+            0b01_0111 => "SMC from AArch64",
+            0b01_1000 => "Trapped MSR/MRS/system instruction",
+            ec::INSTRUCTION_ABORT_LOWER_EL => "Instruction Abort, lower EL",
+            ec::INSTRUCTION_ABORT_CURRENT_EL => "Instruction Abort, current EL",
+            0b10_0010 => "PC alignment fault",
+            ec::DATA_ABORT_LOWER_EL => "Data Abort, lower EL",
+            ec::DATA_ABORT_CURRENT_EL => "Data Abort, current EL",
+            0b10_0110 => "SP alignment fault",
+            0b10_1100 => "Trapped floating point exception",
+            0b10_1111 => "SError interrupt",
+            0b11_1100 => "BRK instruction",
+            _ => "N/A",
+        };
+        writeln!(f, " - {}", ec_translation)?;
+
+        // Raw print of instruction specific syndrome.
+        write!(f, "      Instr Specific Syndrome (ISS): {:#x}", self.0.read(ESR::ISS))?;
+
+        if self.is_abort() {
+            writeln!(f)?;
+            self.fmt_abort_iss(f)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl EsrEL2 {
+    /// Decode the ISS of instruction and data aborts
+    #[rustfmt::skip]
+    fn fmt_abort_iss(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let iss = self.0.read(ESR::ISS);
+        let fault_status = iss & 0b11_1111;
+        let level = fault_status & 0b11;
+
+        let status = match fault_status >> 2 {
+            0b0000 => "Address size fault",
+            0b0001 => "Translation fault",
+            0b0010 => "Access flag fault",
+            0b0011 => "Permission fault",
+            _ => match fault_status {
+                0b01_0000 => "Synchronous External abort",
+                0b10_0001 => "Alignment fault",
+                0b11_0000 => "TLB conflict abort",
+                _ => "N/A",
+            },
+        };
+        write!(f, "            Fault Status Code: {:#04x} - {}", fault_status, status)?;
+        if fault_status >> 2 <= 0b0011 {
+            write!(f, ", level {}", level)?;
+        }
+
+        if matches!(self.exception_class(), ec::DATA_ABORT_LOWER_EL | ec::DATA_ABORT_CURRENT_EL) {
+            let access = if iss & (1 << 6) != 0 { "Write" } else { "Read" };
+            writeln!(f)?;
+            write!(f, "            Access: {}", access)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Human readable print of the exception context.
+///
+/// Note: This will be printed in a panic, so it doesn't bother with the colours or sensible
+/// alignment, just readability.
+impl fmt::Display for ExceptionContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.esr_el2)?;
+
+        if self.esr_el2.far_valid() {
+            writeln!(f, "FAR_EL2: {:#018x}", self.far_el2)?;
+        } else {
+            writeln!(f, "FAR_EL2: {:#018x} (not valid for this exception)", self.far_el2)?;
+        }
+
+        writeln!(f, "{}", self.spsr_el2)?;
+        writeln!(f, "ELR_EL2: {:#018x}", self.elr_el2)?;
+        writeln!(f, "SP_EL2:  {:#018x}", self.sp)?;
+        writeln!(f)?;
+        writeln!(f, "General purpose register:")?;
+
+        #[rustfmt::skip]
+        let alternating = |x| -> _ {
+            if x % 2 == 0 { "   " } else { "\n" }
+        };
+
+        // Print two registers per line.
+        for (i, reg) in self.gpr.iter().enumerate() {
+            write!(f, "      x{: <2}: {: >#018x}{}", i, reg, alternating(i))?;
+        }
+        write!(f, "      lr : {:#018x}", self.lr)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Install the exception vectors and route physical IRQs, FIQs and SErrors to EL2.
+///
+/// # Safety
+///
+/// - Changes the HW state of the executing core.
+/// - The vector table and the "__exception_vector_start" symbol are defined in exception.s, and
+///   the table must stay where it is (the loader is already relocated).
+pub unsafe fn handling_init() {
+    let vectors = __exception_vector_start.get() as u64;
+
+    // Without IMO/FMO/AMO, physical interrupts target EL1 and are never taken while we run at
+    // EL2.
+    let mut hcr: u64;
+    asm!("mrs {}, HCR_EL2", out(reg) hcr, options(nomem, nostack));
+    hcr |= HCR_EL2_FMO_IMO_AMO;
+
+    asm!(
+        "msr VBAR_EL2, {vectors}",
+        "msr HCR_EL2, {hcr}",
+        // Force VBAR and HCR update to complete before next instruction.
+        "isb sy",
+        vectors = in(reg) vectors,
+        hcr = in(reg) hcr,
+        options(nostack),
+    );
+}
+
+/// Undo the interrupt setup of [`handling_init`] before another image takes over the core: mask
+/// IRQs, FIQs and SErrors and give their routing back to EL1. The vectors stay installed, an
+/// image running at EL2 installs its own.
+///
+/// # Safety
+///
+/// - Changes the HW state of the executing core.
+pub unsafe fn prepare_handoff() {
+    // A, I and F
+    asm!("msr DAIFSet, #0b0111", options(nomem, nostack, preserves_flags));
+
+    let mut hcr: u64;
+    asm!("mrs {}, HCR_EL2", out(reg) hcr, options(nomem, nostack));
+    hcr &= !HCR_EL2_FMO_IMO_AMO;
+
+    asm!("msr HCR_EL2, {}", "isb sy", in(reg) hcr, options(nostack));
+}
@@ -0,0 +1,159 @@
+/*
+ * File: exception.s
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 2:10:44 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+/*
+Exception vector table for EL2, installed in VBAR_EL2 by handling_init().
+
+Every entry saves the interrupted context on the stack (see ExceptionContext in exception.rs),
+calls the rust handler of the same name with a pointer to it, restores it and returns.
+An entry is 0x80 bytes (32 instructions), so the whole save sequence fits into it.
+References:
+https://developer.arm.com/documentation/100933/0100/AArch64-exception-vector-table
+*/
+
+// Size of the saved context: x0-x29, lr, ELR_EL2, SPSR_EL2, ESR_EL2, FAR_EL2 and the interrupted
+// stack pointer
+.equ _context_size, 16 * 18
+
+.macro CALL_WITH_CONTEXT handler, on_exception_stack=0
+__vector_\handler:
+.if \on_exception_stack
+    // A stack overflow faults in the guard page below the stack, where the context can't be saved.
+    // Save it on SP_EL0 instead, the core's exception stack (see boot.s), TPIDR_EL2 holds x0
+    // while the interrupted stack pointer is saved. The eret switches back to SP_EL2.
+    msr TPIDR_EL2, x0
+    mov x0,  sp
+    msr SPSel, #0
+    sub sp,  sp,  #_context_size
+    str x0,       [sp, #16 * 17 + 8]
+    mrs x0,  TPIDR_EL2
+.else
+    // make room for the context
+    sub sp,  sp,  #_context_size
+.endif
+
+    // save the general purpose registers
+    stp x0,  x1,  [sp, #16 * 0]
+    stp x2,  x3,  [sp, #16 * 1]
+    stp x4,  x5,  [sp, #16 * 2]
+    stp x6,  x7,  [sp, #16 * 3]
+    stp x8,  x9,  [sp, #16 * 4]
+    stp x10, x11, [sp, #16 * 5]
+    stp x12, x13, [sp, #16 * 6]
+    stp x14, x15, [sp, #16 * 7]
+    stp x16, x17, [sp, #16 * 8]
+    stp x18, x19, [sp, #16 * 9]
+    stp x20, x21, [sp, #16 * 10]
+    stp x22, x23, [sp, #16 * 11]
+    stp x24, x25, [sp, #16 * 12]
+    stp x26, x27, [sp, #16 * 13]
+    stp x28, x29, [sp, #16 * 14]
+
+    // the exception's syndrome: return address, saved PSTATE, cause and faulting address
+    mrs x1,  ELR_EL2
+    mrs x2,  SPSR_EL2
+    mrs x3,  ESR_EL2
+    mrs x4,  FAR_EL2
+    stp lr,  x1,  [sp, #16 * 15]
+    stp x2,  x3,  [sp, #16 * 16]
+.if \on_exception_stack
+    str x4,       [sp, #16 * 17]
+.else
+    // and the stack pointer, right above the context
+    add x5,  sp,  #_context_size
+    stp x4,  x5,  [sp, #16 * 17]
+.endif
+
+    // x0 is the first argument: &mut ExceptionContext
+    mov x0,  sp
+    bl  \handler
+    b   __exception_restore_context
+
+.size __vector_\handler, . - __vector_\handler
+.type __vector_\handler, function
+.endm
+
+.section .text
+
+// VBAR_EL2 ignores the lower 11 bits, the table must be 2KiB aligned.
+.align 11
+
+__exception_vector_start:
+
+// Current EL with SP0. We run on SP_EL2, so only a fault in a synchronous exception handler, which
+// runs on SP_EL0, lands here.
+.org 0x000
+    CALL_WITH_CONTEXT current_el0_synchronous
+.org 0x080
+    CALL_WITH_CONTEXT current_el0_irq
+.org 0x100
+    CALL_WITH_CONTEXT current_el0_fiq
+.org 0x180
+    CALL_WITH_CONTEXT current_el0_serror
+
+// Current EL with SPx: the loader itself
+.org 0x200
+    CALL_WITH_CONTEXT current_elx_synchronous, 1
+.org 0x280
+    CALL_WITH_CONTEXT current_elx_irq
+.org 0x300
+    CALL_WITH_CONTEXT current_elx_fiq
+.org 0x380
+    CALL_WITH_CONTEXT current_elx_serror
+
+// Lower EL using AArch64
+.org 0x400
+    CALL_WITH_CONTEXT lower_aarch64_synchronous
+.org 0x480
+    CALL_WITH_CONTEXT lower_aarch64_irq
+.org 0x500
+    CALL_WITH_CONTEXT lower_aarch64_fiq
+.org 0x580
+    CALL_WITH_CONTEXT lower_aarch64_serror
+
+// Lower EL using AArch32
+.org 0x600
+    CALL_WITH_CONTEXT lower_aarch32_synchronous
+.org 0x680
+    CALL_WITH_CONTEXT lower_aarch32_irq
+.org 0x700
+    CALL_WITH_CONTEXT lower_aarch32_fiq
+.org 0x780
+    CALL_WITH_CONTEXT lower_aarch32_serror
+.org 0x800
+
+// Restore the context saved by CALL_WITH_CONTEXT and return from the exception
+__exception_restore_context:
+    ldr x19,      [sp, #16 * 16]
+    ldp lr,  x20, [sp, #16 * 15]
+
+    msr SPSR_EL2, x19
+    msr ELR_EL2,  x20
+
+    ldp x0,  x1,  [sp, #16 * 0]
+    ldp x2,  x3,  [sp, #16 * 1]
+    ldp x4,  x5,  [sp, #16 * 2]
+    ldp x6,  x7,  [sp, #16 * 3]
+    ldp x8,  x9,  [sp, #16 * 4]
+    ldp x10, x11, [sp, #16 * 5]
+    ldp x12, x13, [sp, #16 * 6]
+    ldp x14, x15, [sp, #16 * 7]
+    ldp x16, x17, [sp, #16 * 8]
+    ldp x18, x19, [sp, #16 * 9]
+    ldp x20, x21, [sp, #16 * 10]
+    ldp x22, x23, [sp, #16 * 11]
+    ldp x24, x25, [sp, #16 * 12]
+    ldp x26, x27, [sp, #16 * 13]
+    ldp x28, x29, [sp, #16 * 14]
+
+    add sp,  sp,  #_context_size
+
+    eret
+
+.size __exception_restore_context, . - __exception_restore_context
+.type __exception_restore_context, function
+.global __exception_vector_start
@@ -0,0 +1,72 @@
+/*
+ * File: asynchronous.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 9:31:18 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Architectural asynchronous exception handling.
+//!
+//! Masking works on the executing core's DAIF flags. Whether an unmasked IRQ is taken at EL2 at
+//! all is up to HCR_EL2.IMO, which the exception vector setup takes care of.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::exception::asynchronous::arch_asynchronous
+
+use core::arch::asm;
+use cortex_a::registers::DAIF;
+use tock_registers::interfaces::{Readable, Writeable};
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Returns whether IRQs are masked on the executing core
+pub fn is_local_irq_masked() -> bool {
+    DAIF.is_set(DAIF::I)
+}
+
+/// Unmask IRQs on the executing core.
+///
+/// It is not needed to place an explicit instruction synchronization barrier after the `msr`.
+/// Quoting the Architecture Reference Manual for ARMv8-A, section C5.1.3:
+///
+/// "Writes to PSTATE.{PAN, D, A, I, F} occur in program order without the need for additional
+/// synchronization."
+#[inline(always)]
+pub fn local_irq_unmask() {
+    unsafe {
+        asm!("msr DAIFClr, #2", options(nomem, nostack, preserves_flags));
+    }
+}
+
+/// Mask IRQs on the executing core
+#[inline(always)]
+pub fn local_irq_mask() {
+    unsafe {
+        asm!("msr DAIFSet, #2", options(nomem, nostack, preserves_flags));
+    }
+}
+
+/// Mask IRQs on the executing core and return the previously saved interrupt mask bits (DAIF)
+#[inline(always)]
+pub fn local_irq_mask_save() -> u64 {
+    let saved = DAIF.get();
+    local_irq_mask();
+
+    saved
+}
+
+/// Restore the interrupt mask bits (DAIF) using the callee's argument.
+///
+/// # Invariant
+///
+/// - No sanity checks on the input.
+#[inline(always)]
+pub fn local_irq_restore(saved: u64) {
+    DAIF.set(saved);
+}
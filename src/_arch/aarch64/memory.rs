@@ -0,0 +1,79 @@
+/*
+ * File: memory.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:04:52 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Architectural data cache maintenance, by virtual address.
+//!
+//! The caches of the ARM cores aren't coherent with the DMA engine or the VideoCore. Before a
+//! device reads a buffer, the lines the core wrote have to be cleaned (written back to RAM); before
+//! the core reads what a device wrote, stale lines have to be invalidated. Lines are cleaned and
+//! invalidated as a whole, so a buffer sharing a line with other data can lose writes to that data
+//! if it's only invalidated: align such buffers to the cache line.
+//!
+//! With the MMU (and so the data cache) off, all of this is harmless.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::memory::arch_memory
+
+use core::arch::asm;
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Write the range back to RAM, so a device reads what the core wrote
+pub fn clean_dcache_range(start: usize, len: usize) {
+    for_each_line(start, len, |addr| unsafe {
+        asm!("dc cvac, {}", in(reg) addr, options(nostack, preserves_flags))
+    });
+}
+
+/// Discard the range's lines, so the core reads what a device wrote. Dirty lines are lost.
+pub fn invalidate_dcache_range(start: usize, len: usize) {
+    for_each_line(start, len, |addr| unsafe {
+        asm!("dc ivac, {}", in(reg) addr, options(nostack, preserves_flags))
+    });
+}
+
+/// Write the range back to RAM and discard its lines
+pub fn clean_invalidate_dcache_range(start: usize, len: usize) {
+    for_each_line(start, len, |addr| unsafe {
+        asm!("dc civac, {}", in(reg) addr, options(nostack, preserves_flags))
+    });
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Smallest data cache line of any cache level (CTR_EL0.DminLine, log2 of the number of words)
+fn dcache_line_size() -> usize {
+    let ctr: u64;
+    unsafe { asm!("mrs {}, CTR_EL0", out(reg) ctr, options(nomem, nostack, preserves_flags)) };
+
+    4 << ((ctr >> 16) & 0xF)
+}
+
+/// Run `op` on every line touching the range, then wait for all of it to complete
+fn for_each_line(start: usize, len: usize, op: impl Fn(usize)) {
+    if len == 0 {
+        return;
+    }
+
+    let line = dcache_line_size();
+    let mut addr = start & !(line - 1);
+
+    while addr < start + len {
+        op(addr);
+        addr += line;
+    }
+
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) };
+}
@@ -0,0 +1,218 @@
+/*
+ * File: mmu.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:16:48 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Memory Management Unit Driver.
+//!
+//! The loader runs at EL2, so it is the EL2 translation regime that is set up: MAIR_EL2, TCR_EL2,
+//! TTBR0_EL2 and SCTLR_EL2. There is no TTBR1 at EL2, the whole address space is TTBR0's.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::memory::mmu::arch_mmu
+
+use crate::{
+    bsp, memory,
+    memory::mmu::{translation_table::KernelTranslationTable, MMUEnableError, TranslationGranule},
+};
+use core::arch::asm;
+use cortex_a::registers::ID_AA64MMFR0_EL1;
+use tock_registers::{interfaces::Readable, register_bitfields};
+
+// Turning the MMU off, see mmu.s
+core::arch::global_asm!(include_str!("mmu.s"));
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_bitfields! {
+    u64,
+
+    /// Translation Control Register (EL2, without VHE)
+    TCR [
+        /// Top Byte Ignored
+        TBI OFFSET(20) NUMBITS(1) [],
+
+        /// Physical address size
+        PS OFFSET(16) NUMBITS(3) [
+            Bits_32 = 0b000
+        ],
+
+        /// Granule size
+        TG0 OFFSET(14) NUMBITS(2) [
+            KiB_64 = 0b01
+        ],
+
+        /// Shareability of table walks
+        SH0 OFFSET(12) NUMBITS(2) [
+            Inner = 0b11
+        ],
+
+        /// Outer and inner cacheability of table walks
+        ORGN0 OFFSET(10) NUMBITS(2) [
+            WriteBack_ReadAlloc_WriteAlloc_Cacheable = 0b01
+        ],
+        IRGN0 OFFSET(8) NUMBITS(2) [
+            WriteBack_ReadAlloc_WriteAlloc_Cacheable = 0b01
+        ],
+
+        /// Size offset: the address space is 2^(64 - T0SZ) bytes
+        T0SZ OFFSET(0) NUMBITS(6) []
+    ],
+
+    /// System Control Register (EL2)
+    SCTLR [
+        /// Instruction cache enable
+        I OFFSET(12) NUMBITS(1) [],
+        /// Data cache enable
+        C OFFSET(2) NUMBITS(1) [],
+        /// MMU enable
+        M OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+/// TCR_EL2 bits 31 and 23 are RES1
+const TCR_EL2_RES1: u64 = 1 << 31 | 1 << 23;
+
+/// Memory Management Unit type
+struct MemoryManagementUnit;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+pub type Granule512MiB = TranslationGranule<{ 512 * 1024 * 1024 }>;
+pub type Granule64KiB = TranslationGranule<{ 64 * 1024 }>;
+
+/// Constants for indexing the MAIR_EL2
+pub mod mair {
+    /// Normal memory, outer and inner write-back, read and write allocate
+    pub const NORMAL: u64 = 0;
+    /// Device-nGnRE
+    pub const DEVICE: u64 = 1;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+/// The translation tables.
+///
+/// # Safety
+///
+/// - Supposed to land in `.bss`. Therefore, ensure that all initial member values boil down to
+///   "0".
+static mut KERNEL_TABLES: KernelTranslationTable = KernelTranslationTable::new();
+
+static MMU: MemoryManagementUnit = MemoryManagementUnit;
+
+extern "C" {
+    fn __disable_mmu_and_clean_caches();
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl MemoryManagementUnit {
+    /// Setup function for the MAIR_EL2 register.
+    unsafe fn set_up_mair(&self) {
+        // attr0: normal, attr1: Device-nGnRE
+        let mair: u64 = 0xFF | 0x04 << 8;
+
+        asm!("msr MAIR_EL2, {}", in(reg) mair, options(nomem, nostack, preserves_flags));
+    }
+
+    /// Configure various settings of stage 1 of the EL2 translation regime.
+    unsafe fn configure_translation_control(&self) {
+        let t0sz = (64 - bsp::memory::mmu::KernelAddrSpace::SIZE_SHIFT) as u64;
+
+        let tcr = TCR_EL2_RES1
+            | (TCR::TBI::CLEAR
+                + TCR::PS::Bits_32
+                + TCR::TG0::KiB_64
+                + TCR::SH0::Inner
+                + TCR::ORGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+                + TCR::IRGN0::WriteBack_ReadAlloc_WriteAlloc_Cacheable
+                + TCR::T0SZ.val(t0sz))
+            .value;
+
+        asm!("msr TCR_EL2, {}", in(reg) tcr, options(nomem, nostack, preserves_flags));
+    }
+
+    fn sctlr(&self) -> u64 {
+        let sctlr: u64;
+        unsafe {
+            asm!("mrs {}, SCTLR_EL2", out(reg) sctlr, options(nomem, nostack, preserves_flags))
+        };
+
+        sctlr
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the MMU instance
+pub fn mmu() -> &'static impl memory::mmu::interface::MMU {
+    &MMU
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl memory::mmu::interface::MMU for MemoryManagementUnit {
+    unsafe fn enable_mmu_and_caching(&self) -> Result<(), MMUEnableError> {
+        if self.is_enabled() {
+            return Err(MMUEnableError::AlreadyEnabled);
+        }
+
+        // Fail early if translation granule is not supported.
+        if !ID_AA64MMFR0_EL1.matches_all(ID_AA64MMFR0_EL1::TGran64::Supported) {
+            return Err(MMUEnableError::Other("Translation granule not supported in HW"));
+        }
+
+        // Prepare the memory attribute indirection register.
+        self.set_up_mair();
+
+        // Populate translation tables.
+        let tables = &mut *core::ptr::addr_of_mut!(KERNEL_TABLES);
+        tables.populate_tt_entries().map_err(MMUEnableError::Other)?;
+
+        // Set the "Translation Table Base Register".
+        let ttbr = tables.phys_base_address();
+        asm!("msr TTBR0_EL2, {}", in(reg) ttbr, options(nomem, nostack, preserves_flags));
+
+        self.configure_translation_control();
+
+        // Drop whatever the firmware may have left in the TLB, and make sure the tables are
+        // written before the first walk.
+        asm!("tlbi alle2", "dsb sy", "isb sy", options(nostack, preserves_flags));
+
+        // Enable the MMU and turn on data and instruction caching.
+        let sctlr = self.sctlr() | (SCTLR::M::SET + SCTLR::C::SET + SCTLR::I::SET).value;
+        asm!("msr SCTLR_EL2, {}", "isb sy", in(reg) sctlr, options(nostack, preserves_flags));
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn is_enabled(&self) -> bool {
+        self.sctlr() & SCTLR::M::SET.value != 0
+    }
+
+    unsafe fn disable_mmu_and_caching(&self) {
+        if self.is_enabled() {
+            __disable_mmu_and_clean_caches();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * File: mmu.s
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:31:14 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+/*
+Turning the MMU and the caches off, at EL2.
+
+Written in assembly because of the window between turning the data cache off and cleaning it:
+from then on, loads go straight to RAM, so anything still dirty in the cache (the stack!) would be
+read stale. __disable_mmu_and_clean_caches doesn't touch memory, and cleans everything while the
+cache is still on.
+
+Cleaning by set/way only works on the executing core's caches, which is fine as long as it is the
+only core running.
+*/
+
+// Clean and invalidate all data and unified caches, up to the point of coherency, by set/way.
+// As in the ARMv8-A Architecture Reference Manual's example. Clobbers x0-x11, x16 and x17.
+.macro DCACHE_CLEAN_INVALIDATE_ALL
+	mrs	x0, CLIDR_EL1
+	and	w3, w0, #0x07000000	// level of coherency
+	lsr	w3, w3, #23		// ... times two
+	cbz	w3, 4f
+	mov	w10, #0			// current level, times two
+	mov	w8, #1
+1:
+	add	w2, w10, w10, lsr #1	// current level, times three
+	lsr	w1, w0, w2		// cache type of the level
+	and	w1, w1, #0x7
+	cmp	w1, #2
+	b.lt	3f			// no data cache at this level
+	msr	CSSELR_EL1, x10		// select the level
+	isb
+	mrs	x1, CCSIDR_EL1
+	and	w2, w1, #7		// log2(line length) - 4
+	add	w2, w2, #4		// log2(line length)
+	ubfx	w4, w1, #3, #10		// number of ways - 1
+	clz	w5, w4			// way position in the set/way operand
+	lsl	w9, w4, w5		// way, shifted
+	lsl	w16, w8, w5		// way decrement, shifted
+2:
+	ubfx	w7, w1, #13, #15	// number of sets - 1
+	lsl	w7, w7, w2		// set, shifted
+	lsl	w17, w8, w2		// set decrement, shifted
+5:
+	orr	w11, w10, w9		// level | way
+	orr	w11, w11, w7		// ... | set
+	dc	cisw, x11
+	subs	w7, w7, w17		// next set
+	b.ge	5b
+	subs	x9, x9, x16		// next way
+	b.ge	2b
+3:
+	add	w10, w10, #2		// next level
+	cmp	w3, w10
+	dsb	sy
+	b.gt	1b
+4:
+.endm
+
+.section .text.__disable_mmu_and_clean_caches
+
+// fn __disable_mmu_and_clean_caches()
+__disable_mmu_and_clean_caches:
+	// everything written so far reaches RAM
+	DCACHE_CLEAN_INVALIDATE_ALL
+
+	// MMU, data and instruction caches off
+	mrs	x0, SCTLR_EL2
+	bic	x0, x0, #(1 << 0)
+	bic	x0, x0, #(1 << 2)
+	bic	x0, x0, #(1 << 12)
+	msr	SCTLR_EL2, x0
+	isb
+
+	// drop the lines speculatively fetched in between, they'd be stale for the next user
+	DCACHE_CLEAN_INVALIDATE_ALL
+
+	ic	iallu
+	tlbi	alle2
+	dsb	sy
+	isb
+	ret
+
+.size	__disable_mmu_and_clean_caches, . - __disable_mmu_and_clean_caches
+.type	__disable_mmu_and_clean_caches, function
+.global	__disable_mmu_and_clean_caches
@@ -0,0 +1,210 @@
+/*
+ * File: psci.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:02:44 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Architectural part of the resident PSCI handler.
+//!
+//! The handler itself is psci.s. What it needs from the loader is filled in by [`install`] into
+//! its `ResidentData`: the HCR_EL2 value for EL1, the watchdog's address and the initial state of
+//! each core.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::psci::arch_psci
+
+use crate::{bsp, cpu, memory, println, psci, time::Deadline};
+use core::{cell::UnsafeCell, mem::size_of, time::Duration};
+
+// The handler, see psci.s
+core::arch::global_asm!(include_str!("psci.s"));
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// HCR_EL2: EL1 is AArch64
+const HCR_EL2_RW: u64 = 1 << 31;
+
+/// HCR_EL2: SMC at EL1 traps to EL2
+#[cfg(feature = "psci_smc")]
+const HCR_EL2_TSC: u64 = 1 << 19;
+
+/// Core states, as AFFINITY_INFO reports them (STATE_* in psci.s)
+mod state {
+    pub const ON: u64 = 0;
+    pub const OFF: u64 = 1;
+}
+
+/// How long a core gets to reach the handler, and to come up and go down again in the self test
+const CORE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// A core's state in the handler, CORE_* in psci.s
+#[repr(C)]
+struct CoreSlot {
+    state: u64,
+
+    /// Where CPU_ON starts the core, and the x0 it gets there
+    entry: u64,
+    context_id: u64,
+
+    /// Set by `__psci_self_test`, see [`install`]
+    self_test: u64,
+}
+
+/// The handler's data, DATA_* in psci.s
+#[repr(C)]
+struct ResidentData {
+    hcr_el2: u64,
+
+    /// The BCM power management block, with the watchdog
+    pm_base: u64,
+
+    cores: [CoreSlot; bsp::cpu::NUM_CORES],
+}
+
+// psci.s has room for 4 cores
+const _: () = assert!(size_of::<ResidentData>() == 16 + 4 * 32);
+
+// Symbols from psci.s
+extern "Rust" {
+    static __psci_data: UnsafeCell<ResidentData>;
+    static __psci_cpu_park: UnsafeCell<()>;
+    static __psci_self_test: UnsafeCell<()>;
+}
+
+extern "C" {
+    fn __psci_boot(entry: usize, dtb: usize) -> !;
+    fn __psci_cpu_on_local(target_cpu: u64, entry: u64, context_id: u64) -> i64;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Fill in the handler's data, and move the secondary cores from the spin table into the handler,
+/// where they wait for a CPU_ON. Cores that start an image of their own (`runs_own_image`) stay
+/// on the spin table, and count as on.
+///
+/// Each core moved is then started once through the handler's own CPU_ON, into a stub that turns
+/// it off again, so a core that wouldn't come up for the image is reported now. It, and any core
+/// that never reached the handler, counts as on: a CPU_ON for it fails instead of waiting forever.
+///
+/// # Safety
+///
+/// - Only the boot core may run, and only once, right before the handoff: the secondary cores
+///   leave the spin table for good.
+pub unsafe fn install(runs_own_image: impl Fn(usize) -> bool) {
+    let moves = |core: usize| {
+        core != cpu::core_id() && !runs_own_image(core) && cpu::smp::is_parked(core)
+    };
+
+    {
+        let data = &mut *__psci_data.get();
+
+        data.hcr_el2 = match psci::CONDUIT {
+            #[cfg(not(feature = "psci_smc"))]
+            psci::Conduit::Hvc => HCR_EL2_RW,
+            #[cfg(feature = "psci_smc")]
+            psci::Conduit::Smc => HCR_EL2_RW | HCR_EL2_TSC,
+        };
+        data.pm_base = bsp::memory::map::mmio::WATCHDOG_START as u64;
+
+        for (core, slot) in data.cores.iter_mut().enumerate() {
+            slot.state = if moves(core) { state::OFF } else { state::ON };
+            slot.entry = 0;
+            slot.context_id = 0;
+            slot.self_test = 0;
+        }
+    }
+
+    // The secondary cores run with the caches off. The data must be in RAM before they can see
+    // their release address, and that before they are woken up.
+    clean_data();
+
+    let park = __psci_cpu_park.get() as u64;
+    for core in (0..bsp::cpu::NUM_CORES).filter(|core| moves(*core)) {
+        core::ptr::write_volatile(bsp::cpu::spin_table_release_addr(core) as *mut u64, park);
+    }
+    memory::clean_dcache_range(bsp::cpu::spin_table_release_addr(0), 8 * bsp::cpu::NUM_CORES);
+    cpu::wake_waiting_cores();
+
+    for core in (0..bsp::cpu::NUM_CORES).filter(|core| moves(*core)) {
+        if let Err(e) = self_test(core) {
+            println!("PSCI: core {} {}, CPU_ON will refuse it", core, e);
+
+            (*__psci_data.get()).cores[core].state = state::ON;
+            clean_data();
+        }
+    }
+}
+
+/// Start the image at EL1 on the executing core, with the device tree at `dtb`
+///
+/// # Safety
+///
+/// - [`install`] ran.
+/// - The MMU and caches are off, and nothing of the loader is needed anymore.
+pub unsafe fn boot(entry: usize, dtb: usize) -> ! {
+    __psci_boot(entry, dtb)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Write the handler's data back to RAM, where the secondary cores read it
+unsafe fn clean_data() {
+    memory::clean_dcache_range(__psci_data.get() as usize, size_of::<ResidentData>());
+}
+
+/// Read what the secondary cores wrote to the handler's data, straight from RAM
+unsafe fn fetch_data() -> &'static ResidentData {
+    memory::clean_invalidate_dcache_range(__psci_data.get() as usize, size_of::<ResidentData>());
+    &*__psci_data.get()
+}
+
+/// Wait for `core` to reach the handler, then start it with the handler's CPU_ON and wait for it
+/// to turn itself off again
+unsafe fn self_test(core: usize) -> Result<(), &'static str> {
+    let slot = bsp::cpu::spin_table_release_addr(core);
+
+    // the core clears its spin table slot on arrival
+    wait_for(|| {
+        memory::clean_invalidate_dcache_range(slot, 8);
+        core::ptr::read_volatile(slot as *const u64) == 0
+    })
+    .map_err(|_| "didn't reach the handler")?;
+
+    if __psci_cpu_on_local(core as u64, __psci_self_test.get() as u64, 0) != 0 {
+        return Err("was refused by CPU_ON");
+    }
+    // the handler wrote with the caches on
+    clean_data();
+    cpu::wake_waiting_cores();
+
+    wait_for(|| {
+        let slot = &fetch_data().cores[core];
+        core::ptr::read_volatile(&slot.self_test) == 1
+            && core::ptr::read_volatile(&slot.state) == state::OFF
+    })
+    .map_err(|_| "didn't come up through CPU_ON")
+}
+
+/// Poll `done` until it's true, or [`CORE_TIMEOUT`] passed
+fn wait_for(mut done: impl FnMut() -> bool) -> Result<(), ()> {
+    let deadline = Deadline::after(CORE_TIMEOUT);
+
+    while !done() {
+        if deadline.has_passed() {
+            return Err(());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,401 @@
+/*
+ * File: psci.s
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:02:51 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+/*
+The resident PSCI 0.2 handler, at EL2, for an image running at EL1.
+
+Everything it needs lives in the .text._psci section, which link.ld gives pages of their own and
+the device tree handed to the image reserves: the vector table, the code and the per-core state.
+It runs with the EL2 MMU and caches off, so the state is always read from RAM, and without a
+stack: the SMC Calling Convention (1.0) lets it clobber x0-x17, it sticks to x0-x3 and x9-x13.
+References:
+https://developer.arm.com/documentation/den0022/latest
+*/
+
+// Function ids
+.equ PSCI_VERSION,          0x84000000
+.equ PSCI_CPU_OFF,          0x84000002
+.equ PSCI_CPU_ON_32,        0x84000003
+.equ PSCI_CPU_ON_64,        0xc4000003
+.equ PSCI_AFFINITY_INFO_32, 0x84000004
+.equ PSCI_AFFINITY_INFO_64, 0xc4000004
+.equ PSCI_SYSTEM_OFF,       0x84000008
+.equ PSCI_SYSTEM_RESET,     0x84000009
+
+// Return codes
+.equ PSCI_SUCCESS,             0
+.equ PSCI_NOT_SUPPORTED,      -1
+.equ PSCI_INVALID_PARAMETERS, -2
+.equ PSCI_ALREADY_ON,         -4
+.equ PSCI_ON_PENDING,         -5
+
+// Major 0, minor 2
+.equ PSCI_VERSION_0_2, 0x2
+
+// Core states, as AFFINITY_INFO reports them. `state` in psci.rs.
+.equ STATE_ON,         0
+.equ STATE_OFF,        1
+.equ STATE_ON_PENDING, 2
+
+// ResidentData in psci.rs
+.equ DATA_HCR_EL2, 0
+.equ DATA_PM_BASE, 8
+.equ DATA_CORES,   16
+// CoreSlot in psci.rs: state, entry point, context id, self test
+.equ CORE_STATE,      0
+.equ CORE_ENTRY,      8
+.equ CORE_SELF_TEST,  24
+.equ CORE_SIZE_SHIFT, 5
+.equ NUM_CORES,       4
+
+// The watchdog, in the BCM power management block
+.equ PM_RSTC,                  0x1c
+.equ PM_RSTS,                  0x20
+.equ PM_WDOG,                  0x24
+.equ PM_PASSWORD,              0x5a000000
+.equ PM_RSTC_WRCFG_CLR,        0xffffffcf
+.equ PM_RSTC_WRCFG_FULL_RESET, 0x20
+// Boot partition 63: the firmware halts instead of booting
+.equ PM_RSTS_PARTITION_HALT,   0x555
+
+// Exception classes (ESR_EL2.EC)
+.equ EC_HVC64, 0x16
+.equ EC_SMC64, 0x17
+
+// Load a 32 bit constant
+.macro MOV32 reg, value
+	movz	\reg, #((\value) >> 16), lsl #16
+	movk	\reg, #((\value) & 0xffff)
+.endm
+
+// Branch to \label if w0 is the function id \fid. Clobbers w9.
+.macro DISPATCH fid, label
+	MOV32	w9, \fid
+	cmp	w0, w9
+	b.eq	\label
+.endm
+
+// x10 = the slot of the core with the affinity in \mpidr, or branch to \invalid if there is no
+// such core. Clobbers x11.
+.macro CORE_SLOT mpidr, invalid
+	// Aff1-Aff3 are zero on the RPi
+	ubfx	x11, \mpidr, #8, #16
+	cbnz	x11, \invalid
+	ubfx	x11, \mpidr, #32, #8
+	cbnz	x11, \invalid
+	and	x11, \mpidr, #0xff
+	cmp	x11, #NUM_CORES
+	b.hs	\invalid
+	adr	x10, __psci_data + DATA_CORES
+	add	x10, x10, x11, lsl #CORE_SIZE_SHIFT
+.endm
+
+// Enter EL1h at \entry with x0 as it is and x1-x3 zero: all exceptions masked, the EL1 MMU and
+// caches off, as the arm64 boot protocol asks. Clobbers x9.
+.macro ENTER_EL1 entry
+	// EL1 is AArch64, and with the SMC conduit SMC traps to EL2
+	adr	x9, __psci_data
+	ldr	x9, [x9, #DATA_HCR_EL2]
+	msr	HCR_EL2, x9
+
+	// EL1 may use the physical counter and timer, and sees no virtual offset
+	mrs	x9, CNTHCTL_EL2
+	orr	x9, x9, #0b11
+	msr	CNTHCTL_EL2, x9
+	msr	CNTVOFF_EL2, xzr
+
+	// no FP/SIMD or system register traps to EL2
+	mov	x9, #0x33ff
+	msr	CPTR_EL2, x9
+	msr	HSTR_EL2, xzr
+
+	// EL1 reads the real MIDR and MPIDR
+	mrs	x9, MIDR_EL1
+	msr	VPIDR_EL2, x9
+	mrs	x9, MPIDR_EL1
+	msr	VMPIDR_EL2, x9
+
+	// MMU and caches off, the RES1 bits set
+	MOV32	x9, 0x30d00800
+	msr	SCTLR_EL1, x9
+
+	// HVCs and trapped SMCs land here
+	adr	x9, __psci_vectors
+	msr	VBAR_EL2, x9
+
+	// EL1h, D, A, I and F masked
+	mov	x9, #0x3c5
+	msr	SPSR_EL2, x9
+	msr	ELR_EL2, \entry
+
+	mov	x1, xzr
+	mov	x2, xzr
+	mov	x3, xzr
+	isb
+	eret
+.endm
+
+.section .text._psci, "ax"
+
+// VBAR_EL2 ignores the lower 11 bits, the table must be 2KiB aligned.
+.align 11
+
+__psci_vectors:
+
+// Current EL. The handler doesn't fault, nothing should ever land here.
+.org 0x000
+	b	__psci_hang
+.org 0x080
+	b	__psci_hang
+.org 0x100
+	b	__psci_hang
+.org 0x180
+	b	__psci_hang
+.org 0x200
+	b	__psci_hang
+.org 0x280
+	b	__psci_hang
+.org 0x300
+	b	__psci_hang
+.org 0x380
+	b	__psci_hang
+
+// Lower EL, AArch64: the image. Interrupts are routed to EL1, only HVC and SMC come here.
+.org 0x400
+	b	__psci_lower_aarch64_synchronous
+.org 0x480
+	b	__psci_hang
+.org 0x500
+	b	__psci_hang
+.org 0x580
+	b	__psci_hang
+
+// Lower EL, AArch32: not supported
+.org 0x600
+	b	__psci_hang
+.org 0x680
+	b	__psci_hang
+.org 0x700
+	b	__psci_hang
+.org 0x780
+	b	__psci_hang
+.org 0x800
+
+__psci_lower_aarch64_synchronous:
+	mrs	x9, ESR_EL2
+	lsr	x9, x9, #26
+	cmp	x9, #EC_HVC64
+	b.eq	1f
+	cmp	x9, #EC_SMC64
+	b.ne	__psci_hang
+
+	// a trapped SMC returns to itself, not to the instruction after it
+	mrs	x9, ELR_EL2
+	add	x9, x9, #4
+	msr	ELR_EL2, x9
+1:
+	DISPATCH PSCI_VERSION,          __psci_version
+	DISPATCH PSCI_CPU_OFF,          __psci_cpu_off
+	DISPATCH PSCI_CPU_ON_32,        __psci_cpu_on_32
+	DISPATCH PSCI_CPU_ON_64,        __psci_cpu_on
+	DISPATCH PSCI_AFFINITY_INFO_32, __psci_affinity_info_32
+	DISPATCH PSCI_AFFINITY_INFO_64, __psci_affinity_info
+	DISPATCH PSCI_SYSTEM_OFF,       __psci_system_off
+	DISPATCH PSCI_SYSTEM_RESET,     __psci_system_reset
+
+	mov	x0, #PSCI_NOT_SUPPORTED
+	eret
+
+__psci_version:
+	mov	x0, #PSCI_VERSION_0_2
+	eret
+
+// CPU_ON(target_cpu, entry_point_address, context_id)
+__psci_cpu_on_32:
+	// only the lower halves of the arguments count
+	mov	w1, w1
+	mov	w2, w2
+	mov	w3, w3
+__psci_cpu_on:
+	CORE_SLOT x1, __psci_invalid_parameters
+
+	ldr	x12, [x10, #CORE_STATE]
+	cmp	x12, #STATE_ON
+	b.eq	__psci_already_on
+	cmp	x12, #STATE_ON_PENDING
+	b.eq	__psci_on_pending
+
+	stp	x2, x3, [x10, #CORE_ENTRY]
+	// the entry point is in place before the target sees the new state
+	dsb	sy
+	mov	x12, #STATE_ON_PENDING
+	str	x12, [x10, #CORE_STATE]
+	dsb	sy
+	sev
+
+	mov	x0, #PSCI_SUCCESS
+	eret
+
+// CPU_OFF(): wait with the other parked cores until a CPU_ON for this one
+__psci_cpu_off:
+	mrs	x12, MPIDR_EL1
+	CORE_SLOT x12, __psci_hang
+
+	mov	x12, #STATE_OFF
+	str	x12, [x10, #CORE_STATE]
+	dsb	sy
+	b	__psci_wait_for_cpu_on
+
+// AFFINITY_INFO(target_affinity, lowest_affinity_level)
+__psci_affinity_info_32:
+	mov	w1, w1
+	mov	w2, w2
+__psci_affinity_info:
+	// there is nothing but cores to report on
+	cbnz	x2, __psci_invalid_parameters
+	CORE_SLOT x1, __psci_invalid_parameters
+
+	ldr	x0, [x10, #CORE_STATE]
+	eret
+
+// SYSTEM_OFF(): a reset into boot partition 63, which the firmware halts on, like Linux's
+// bcm2835_wdt does it
+__psci_system_off:
+	adr	x10, __psci_data
+	ldr	x10, [x10, #DATA_PM_BASE]
+
+	ldr	w11, [x10, #PM_RSTS]
+	MOV32	w12, PM_PASSWORD | PM_RSTS_PARTITION_HALT
+	orr	w11, w11, w12
+	str	w11, [x10, #PM_RSTS]
+
+// SYSTEM_RESET(): let the watchdog run out after 10 ticks (~150us), with a full reset
+__psci_system_reset:
+	adr	x10, __psci_data
+	ldr	x10, [x10, #DATA_PM_BASE]
+
+	MOV32	w11, PM_PASSWORD | 10
+	str	w11, [x10, #PM_WDOG]
+	ldr	w11, [x10, #PM_RSTC]
+	and	w11, w11, #PM_RSTC_WRCFG_CLR
+	MOV32	w12, PM_PASSWORD | PM_RSTC_WRCFG_FULL_RESET
+	orr	w11, w11, w12
+	str	w11, [x10, #PM_RSTC]
+1:
+	wfi
+	b	1b
+
+__psci_invalid_parameters:
+	mov	x0, #PSCI_INVALID_PARAMETERS
+	eret
+
+__psci_already_on:
+	mov	x0, #PSCI_ALREADY_ON
+	eret
+
+__psci_on_pending:
+	mov	x0, #PSCI_ON_PENDING
+	eret
+
+// Anything the handler can't make sense of stops the core
+__psci_hang:
+	wfe
+	b	__psci_hang
+
+// Where the secondary cores wait for a CPU_ON, released into it from the spin table by
+// psci::install(). At EL2, MMU and caches off.
+__psci_cpu_park:
+	mrs	x12, MPIDR_EL1
+	CORE_SLOT x12, __psci_hang
+
+	// clearing its spin table slot tells psci::install() the core arrived
+	and	x12, x12, #0xff
+	ldr	x11, SPIN_TABLE_START
+	str	xzr, [x11, x12, lsl #3]
+	dsb	sy
+
+// x10: the core's slot
+__psci_wait_for_cpu_on:
+	// check before waiting, the CPU_ON may have come first
+	ldr	x12, [x10, #CORE_STATE]
+	cmp	x12, #STATE_ON_PENDING
+	b.eq	1f
+	wfe
+	b	__psci_wait_for_cpu_on
+1:
+	ldp	x13, x0, [x10, #CORE_ENTRY]
+	mov	x12, #STATE_ON
+	str	x12, [x10, #CORE_STATE]
+	dsb	sy
+	ENTER_EL1 x13
+
+.size	__psci_cpu_park, . - __psci_cpu_park
+.type	__psci_cpu_park, function
+.global	__psci_cpu_park
+
+// fn __psci_boot(entry: usize, dtb: usize) -> !
+// The boot core's way into the image, with the device tree in x0
+__psci_boot:
+	mov	x13, x0
+	mov	x0, x1
+	ENTER_EL1 x13
+
+.size	__psci_boot, . - __psci_boot
+.type	__psci_boot, function
+.global	__psci_boot
+
+// fn __psci_cpu_on_local(target_cpu: u64, entry: u64, context_id: u64) -> i64
+// psci::install()'s way to make a CPU_ON, from EL2: the handler's own, returning here
+__psci_cpu_on_local:
+	mov	x3, x2
+	mov	x2, x1
+	mov	x1, x0
+
+	// the handler's eret comes back at EL2h with the caller's DAIF, nothing may take an
+	// exception and overwrite ELR_EL2 and SPSR_EL2 before
+	mrs	x9, DAIF
+	msr	DAIFSet, #0xf
+	// EL2h
+	mov	x10, #0x9
+	orr	x9, x9, x10
+	msr	SPSR_EL2, x9
+	adr	x9, 1f
+	msr	ELR_EL2, x9
+	b	__psci_cpu_on
+1:
+	ret
+
+.size	__psci_cpu_on_local, . - __psci_cpu_on_local
+.type	__psci_cpu_on_local, function
+.global	__psci_cpu_on_local
+
+// psci::install()'s CPU_ON self test: started at EL1 like a core of the image, it notes that it
+// ran and turns itself off again
+__psci_self_test:
+	mrs	x12, MPIDR_EL1
+	CORE_SLOT x12, __psci_hang
+
+	mov	x12, #1
+	str	x12, [x10, #CORE_SELF_TEST]
+	dsb	sy
+
+	MOV32	w0, PSCI_CPU_OFF
+	hvc	#0
+	b	__psci_hang
+
+.size	__psci_self_test, . - __psci_self_test
+.type	__psci_self_test, function
+.global	__psci_self_test
+
+// ResidentData in psci.rs, filled in by psci::install()
+.balign 8
+__psci_data:
+	.space	DATA_CORES + (NUM_CORES << CORE_SIZE_SHIFT)
+
+.size	__psci_data, . - __psci_data
+.type	__psci_data, object
+.global	__psci_data
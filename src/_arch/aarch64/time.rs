@@ -0,0 +1,110 @@
+/*
+ * File: time.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 8:11:02 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Architectural timer primitives.
+//!
+//! Backed by the ARM generic timer: CNTPCT_EL0 counts at CNTFRQ_EL0 Hz since power-on, which the
+//! firmware sets up before we run (19.2 MHz on the RPi3, 54 MHz on the RPi4). Firmware that leaves
+//! CNTFRQ_EL0 at zero gets the board's frequency instead of a division by zero in every delay.
+//! Only EL3 can write CNTFRQ_EL0, so there is no fixing it from here.
+//!
+//! # Orientation
+//!
+//! Since arch modules are imported into generic modules using the path attribute, the path of this
+//! file is:
+//!
+//! crate::time::arch_time
+
+use crate::{bsp, time};
+use core::time::Duration;
+use cortex_a::{
+    asm::barrier,
+    registers::{CNTFRQ_EL0, CNTPCT_EL0},
+};
+use tock_registers::interfaces::Readable;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+const NS_PER_S: u64 = 1_000_000_000;
+
+struct GenericTimer;
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static TIME_MANAGER: GenericTimer = GenericTimer;
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl GenericTimer {
+    #[inline(always)]
+    fn read_cntpct(&self) -> u64 {
+        // Prevent that the counter is read ahead of time due to out-of-order execution.
+        barrier::isb(barrier::SY);
+        CNTPCT_EL0.get()
+    }
+
+    #[inline(always)]
+    fn frequency(&self) -> u64 {
+        match CNTFRQ_EL0.get() {
+            0 => bsp::cpu::TIMER_FREQUENCY_HZ,
+            frequency => frequency,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the time manager.
+pub fn time_manager() -> &'static impl time::interface::TimeManager {
+    &TIME_MANAGER
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl time::interface::TimeManager for GenericTimer {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(NS_PER_S / self.frequency())
+    }
+
+    fn uptime(&self) -> Duration {
+        let ticks = self.read_cntpct();
+        let frequency = self.frequency();
+
+        // split to not overflow the multiplication
+        let secs = ticks / frequency;
+        let sub_second_ns = (ticks % frequency) * NS_PER_S / frequency;
+
+        Duration::new(secs, sub_second_ns as u32)
+    }
+
+    fn spin_for(&self, duration: Duration) {
+        if duration.as_nanos() == 0 {
+            return;
+        }
+
+        let frequency = self.frequency() as u128;
+        // more than ~30000 years at 19.2 MHz is forever
+        let ticks =
+            u64::try_from(duration.as_nanos() * frequency / NS_PER_S as u128).unwrap_or(u64::MAX);
+
+        // round up, so very short waits still wait
+        let ticks = ticks.max(1);
+        let start = self.read_cntpct();
+
+        while self.read_cntpct().wrapping_sub(start) < ticks {}
+    }
+}
@@ -0,0 +1,62 @@
+/*
+ * File: autoboot.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 10:20:44 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Autoboot: give the host a few seconds to answer, then boot a fallback image.
+//!
+//! The countdown (`AUTOBOOT_SECS`) and the fallback (`AUTOBOOT_FALLBACK`) are set at build time,
+//! see [`crate::config`]. A keypress on the console during the countdown drops to the monitor.
+
+use crate::{
+    boot_mode::BootMode,
+    config,
+    loader::{self, Image, NoImage},
+    monitor, println,
+    time::Deadline,
+};
+use core::time::Duration;
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Wait for the host until the countdown runs out, then boot the fallback image. Never returns.
+pub fn run(boot_mode: BootMode) -> ! {
+    if config::AUTOBOOT_SECS == 0 {
+        loader::boot(loader::receive_from_host());
+    }
+
+    let countdown = Deadline::after(Duration::from_secs(config::AUTOBOOT_SECS));
+
+    match loader::receive_from_host_until(countdown) {
+        Ok(image) => loader::boot(image),
+        Err(NoImage::KeyPressed) => {
+            println!("Autoboot aborted");
+            monitor::run(boot_mode)
+        }
+        Err(NoImage::TimedOut) => match fallback_image() {
+            Ok(image) => {
+                println!("No host answered, booting the fallback image");
+                loader::boot(image)
+            }
+            Err(e) => {
+                println!("No host answered and no fallback image ({}), waiting for the host", e);
+                loader::boot(loader::receive_from_host())
+            }
+        },
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+fn fallback_image() -> Result<Image, &'static str> {
+    match config::AUTOBOOT_FALLBACK {
+        Some(fallback) => fallback(),
+        None => Err("autoboot fallback disabled"),
+    }
+}
@@ -0,0 +1,43 @@
+/*
+ * File: boot_mode.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 2:05:47 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Loader boot modes.
+//!
+//! The mode is chosen at power-on by the board's strap pins (see `bsp::boot_mode()`), so it
+//! can be forced without touching the host side.
+
+use core::fmt;
+
+/// What the loader does after printing its banner
+#[derive(Copy, Clone, PartialEq)]
+pub enum BootMode {
+    /// Request an image from the host, boot the fallback image if none answers in time (default,
+    /// no strap jumpered)
+    Normal,
+    /// Wait for the host forever
+    WaitForHost,
+    /// Boot the image cached in RAM by a previous session
+    BootCached,
+    /// Boot the image built into the loader
+    BootEmbedded,
+    /// Enter the interactive monitor
+    Monitor,
+}
+
+impl fmt::Display for BootMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BootMode::Normal => "normal",
+            BootMode::WaitForHost => "wait for host",
+            BootMode::BootCached => "boot cached image",
+            BootMode::BootEmbedded => "boot embedded image",
+            BootMode::Monitor => "monitor",
+        };
+
+        f.write_str(name)
+    }
+}
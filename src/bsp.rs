@@ -5,13 +5,13 @@
  * Author: Elad Matia (elad.matia@gmail.com)
  */
 //! board specific code
-//! reexport board specific code (only RPi 3 for now)
+//! reexport board specific code (RPi 3 and RPi 4)
 
 mod device_driver;
 
 
-#[cfg(feature="bsp_rpi3")]
+#[cfg(any(feature="bsp_rpi3", feature="bsp_rpi4"))]
 mod raspberrypi;
 
-#[cfg(feature="bsp_rpi3")]
+#[cfg(any(feature="bsp_rpi3", feature="bsp_rpi4"))]
 pub use raspberrypi::*;
\ No newline at end of file
@@ -0,0 +1,12 @@
+/*
+ * File: arm.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:02:14 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! ARM drivers (the RPI4's GIC-400)
+
+mod gicv2;
+
+pub use gicv2::*;
@@ -0,0 +1,158 @@
+/*
+ * File: gicv2.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:05:26 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! GICv2 driver (the RPi4's GIC-400).
+//!
+//! The GIC has two parts: the distributor (GICD), shared by all cores, which routes and
+//! prioritizes interrupts, and a CPU interface (GICC) per core, through which the core
+//! acknowledges and completes them.
+//!
+//! Interrupt IDs:
+//! - 0-15: software generated interrupts (SGI), banked per core
+//! - 16-31: private peripheral interrupts (PPI), banked per core (e.g. the ARM generic timers)
+//! - 32 and up: shared peripheral interrupts (SPI). On the BCM2711, VideoCore IRQ n is SPI
+//!   96 + n.
+//!
+//! Descriptions taken from
+//! - https://developer.arm.com/documentation/ihi0048/b (GICv2 architecture specification)
+//! - https://developer.arm.com/documentation/ddi0471/b (GIC-400 TRM)
+
+mod gicc;
+mod gicd;
+
+use crate::{
+    driver,
+    exception::{self, asynchronous::{IRQDescriptor, IRQHandlers}},
+    println,
+    synchronization::{interface::Mutex, NullLock},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+type HandlerTable = [IRQHandlers; GICv2::NUM_IRQS];
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Used for the associated type of trait [`exception::asynchronous::interface::IRQManager`]
+pub type IRQNumber = exception::asynchronous::BoundedUsize<{ GICv2::MAX_IRQ_NUMBER }>;
+
+/// Representation of the GIC
+pub struct GICv2 {
+    /// The Distributor
+    gicd: gicd::GICD,
+
+    /// The CPU Interface
+    gicc: gicc::GICC,
+
+    /// Stores registered IRQ handlers
+    handler_table: NullLock<HandlerTable>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl GICv2 {
+    // The GIC-400 on the BCM2711 implements 256 interrupt IDs (GICD_TYPER.ITLinesNumber = 7).
+    const MAX_IRQ_NUMBER: usize = 255;
+    const NUM_IRQS: usize = Self::MAX_IRQ_NUMBER + 1;
+
+    /// Create an instance
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide correct MMIO start addresses
+    pub const unsafe fn new(gicd_mmio_start_addr: usize, gicc_mmio_start_addr: usize) -> Self {
+        Self {
+            gicd: gicd::GICD::new(gicd_mmio_start_addr),
+            gicc: gicc::GICC::new(gicc_mmio_start_addr),
+            handler_table: NullLock::new([IRQHandlers::new(); Self::NUM_IRQS]),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl driver::interface::DeviceDriver for GICv2 {
+    fn compatible(&self) -> &'static str {
+        "GICv2 (ARM Generic Interrupt Controller v2)"
+    }
+
+    fn init(&self) -> Result<(), &'static str> {
+        self.gicd.boot_core_init();
+        self.gicc.priority_accept_all();
+        self.gicc.enable();
+
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        self.gicd.disable_all();
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQManager for GICv2 {
+    type IRQNumberType = IRQNumber;
+
+    fn register_handler(
+        &self,
+        irq_number: Self::IRQNumberType,
+        descriptor: IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        self.handler_table.lock(|table| table[irq_number.get()].add(descriptor))
+    }
+
+    fn enable(&self, irq_number: Self::IRQNumberType) {
+        self.gicd.enable(irq_number);
+    }
+
+    fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        // Extract the highest priority pending IRQ number from the Interrupt Acknowledge Register
+        // (IAR).
+        let irq_number = self.gicc.pending_irq_number(ic);
+
+        // Guard against spurious interrupts.
+        if irq_number > GICv2::MAX_IRQ_NUMBER {
+            return;
+        }
+
+        // Call the IRQ handler. Panic if there is none.
+        self.handler_table.lock(|table| {
+            let handlers = &table[irq_number];
+            if handlers.is_empty() {
+                panic!("No handler registered for IRQ {}", irq_number);
+            }
+
+            // Call the IRQ handlers. Panics on failure.
+            handlers.handle().expect("Error handling IRQ");
+        });
+
+        // Signal completion of handling.
+        self.gicc.mark_completed(irq_number as u32, ic);
+    }
+
+    fn print_handler(&self) {
+        self.handler_table.lock(|table| {
+            for (i, handlers) in table.iter().enumerate() {
+                for name in handlers.names() {
+                    println!("      {: >3}. {}", i, name);
+                }
+            }
+        });
+    }
+}
@@ -7,8 +7,26 @@
 
 //! BCM2xxx drivers (RPI3 is BCM2837)
 
+mod bcm2xxx_dma;
 mod bcm2xxx_gpio;
+#[cfg(feature = "bsp_rpi3")]
+mod bcm2xxx_interrupt_controller;
+#[cfg(feature = "bsp_rpi3")]
+mod bcm2xxx_mailbox;
+#[cfg(feature = "console_mini_uart")]
+mod bcm2xxx_mini_uart;
+#[cfg(pl011)]
 mod bcm2xxx_pl011_uart;
+mod bcm2xxx_system_timer;
 
+pub use bcm2xxx_dma::*;
 pub use bcm2xxx_gpio::*;
-pub use bcm2xxx_pl011_uart::*;
\ No newline at end of file
+#[cfg(feature = "bsp_rpi3")]
+pub use bcm2xxx_interrupt_controller::*;
+#[cfg(feature = "bsp_rpi3")]
+pub use bcm2xxx_mailbox::*;
+#[cfg(feature = "console_mini_uart")]
+pub use bcm2xxx_mini_uart::*;
+#[cfg(pl011)]
+pub use bcm2xxx_pl011_uart::*;
+pub use bcm2xxx_system_timer::*;
\ No newline at end of file
@@ -0,0 +1,604 @@
+/*
+ * File: bcm2xxx_dma.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 6:20:41 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BCM DMA controller driver.
+//!
+//! A channel executes a chain of control blocks (CBs) in RAM: each describes one transfer (source,
+//! destination, length, and how to pace it), and points at the next one. A transfer to or from a
+//! peripheral is paced by the peripheral's DREQ line, so the DMA engine only moves data when the
+//! peripheral is ready for it.
+//!
+//! The DMA engine works on VideoCore bus addresses: RAM is seen through the uncached alias, given
+//! to [`Dma::new`], peripherals at 0x7E00_0000. The ARM's data cache isn't coherent with the DMA
+//! engine: [`Dma::memcpy`] and the control blocks take care of it, users of
+//! [`Dma::start_ring_from_peripheral`] invalidate what they read.
+//!
+//! Only the full channels (0-6) are driven: 7-14 are DMA Lite (and DMA4 on the BCM2711) with a
+//! different feature set, and the firmware keeps some channels for itself, so the BSP picks which
+//! ones the loader may use.
+//!
+//! Descriptions taken from
+//! - https://github.com/raspberrypi/documentation/files/1888662/BCM2837-ARM-Peripherals.-.Revised.-.V2-1.pdf
+//!   (chapter 4, DMA Controller)
+
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper, bsp::exception::asynchronous::IRQNumber, cpu,
+    driver, exception, memory, synchronization::interface::Mutex, synchronization::NullLock,
+    time::Deadline,
+};
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+#[cfg(pl011)]
+use core::sync::atomic::AtomicU32;
+
+use tock_registers::{
+    interfaces::{ReadWriteable, Readable, Writeable},
+    register_bitfields, register_structs,
+    registers::{ReadOnly, ReadWrite},
+};
+
+//----------------------------------------
+// private stuff
+//----------------------------------------
+
+register_bitfields! {
+    u32,
+
+    /// Control and Status
+    CS [
+        /// Reset the channel, self clearing
+        RESET OFFSET(31) NUMBITS(1) [],
+
+        /// Abort the current control block and load the next one, self clearing
+        ABORT OFFSET(30) NUMBITS(1) [],
+
+        /// Wait for outstanding writes before signalling the end of a transfer
+        WAIT_FOR_OUTSTANDING_WRITES OFFSET(28) NUMBITS(1) [],
+
+        /// AXI priority of normal and panicking transfers, 0-15
+        PANIC_PRIORITY OFFSET(20) NUMBITS(4) [],
+        PRIORITY OFFSET(16) NUMBITS(4) [],
+
+        /// The channel hit an error, see its DEBUG register
+        ERROR OFFSET(8) NUMBITS(1) [],
+
+        /// Interrupt status, write 1 to clear
+        INT OFFSET(2) NUMBITS(1) [],
+
+        /// The control block's transfer is complete, write 1 to clear
+        END OFFSET(1) NUMBITS(1) [],
+
+        /// Activate the channel, cleared once the chain ends
+        ACTIVE OFFSET(0) NUMBITS(1) []
+    ],
+
+    /// Transfer Information, the first word of a control block
+    TI [
+        /// Don't do wide writes as 2 beat bursts
+        NO_WIDE_BURSTS OFFSET(26) NUMBITS(1) [],
+
+        /// Peripheral whose DREQ paces the transfer
+        PERMAP OFFSET(16) NUMBITS(5) [],
+
+        /// Burst length minus one, in words
+        BURST_LENGTH OFFSET(12) NUMBITS(4) [],
+
+        /// Source: only read when the peripheral's DREQ is set
+        SRC_DREQ OFFSET(10) NUMBITS(1) [],
+        /// Source: 128 bit reads if set, 32 bit otherwise
+        SRC_WIDTH OFFSET(9) NUMBITS(1) [],
+        /// Source: increment the address after each read
+        SRC_INC OFFSET(8) NUMBITS(1) [],
+
+        /// Destination: only written when the peripheral's DREQ is set
+        DEST_DREQ OFFSET(6) NUMBITS(1) [],
+        /// Destination: 128 bit writes if set, 32 bit otherwise
+        DEST_WIDTH OFFSET(5) NUMBITS(1) [],
+        /// Destination: increment the address after each write
+        DEST_INC OFFSET(4) NUMBITS(1) [],
+
+        /// Wait for the write response before the next read
+        WAIT_RESP OFFSET(3) NUMBITS(1) [],
+
+        /// Raise the channel's interrupt when this control block's transfer ends
+        INTEN OFFSET(0) NUMBITS(1) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    ChannelRegisterBlock {
+        (0x00 => CS: ReadWrite<u32, CS::Register>),
+        // bus address of the control block to execute
+        (0x04 => CONBLK_AD: ReadWrite<u32>),
+        // the current control block, loaded from CONBLK_AD
+        (0x08 => TI: ReadOnly<u32, TI::Register>),
+        (0x0c => SOURCE_AD: ReadOnly<u32>),
+        (0x10 => DEST_AD: ReadOnly<u32>),
+        (0x14 => TXFR_LEN: ReadOnly<u32>),
+        (0x18 => STRIDE: ReadOnly<u32>),
+        (0x1c => NEXTCONBK: ReadOnly<u32>),
+        (0x20 => DEBUG: ReadWrite<u32>),
+        (0x24 => _reserved),
+        (0x100 => @END),
+    }
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        // channels 0-14, 0x100 apart. Channel 15 lives elsewhere.
+        (0x000 => CHANNEL: [ChannelRegisterBlock; 15]),
+        (0xf00 => _reserved1),
+        // bit n is channel n's interrupt status
+        (0xfe0 => INT_STATUS: ReadOnly<u32>),
+        (0xfe4 => _reserved2),
+        // bit n enables channel n
+        (0xff0 => ENABLE: ReadWrite<u32>),
+        (0xff4 => @END),
+    }
+}
+
+// abtracts the register calling
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// Channels 0-6 are full channels
+const NUM_CHANNELS: usize = 7;
+
+/// Longest a memcpy may take before the channel is considered stuck
+const MEMCPY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// TXFR_LEN is 30 bits wide, longer copies take several transfers (of whole words)
+const MAX_TRANSFER_LEN: usize = (1 << 30) - 4;
+
+/// A control block, as the DMA engine reads it. Must be 32 byte aligned.
+#[repr(C, align(32))]
+#[derive(Copy, Clone)]
+struct ControlBlock {
+    ti: u32,
+    source_ad: u32,
+    dest_ad: u32,
+    txfr_len: u32,
+    stride: u32,
+    nextconbk: u32,
+    _reserved: [u32; 2],
+}
+
+//----------------------------------------
+// Public Definitions
+//----------------------------------------
+
+/// Peripherals pacing a transfer with their DREQ line (the PERMAP field). The BCM2711's UART3 and
+/// UART5 share theirs with SPI4 and SPI5.
+#[cfg(pl011)]
+#[derive(Copy, Clone)]
+pub enum Dreq {
+    /// PL011 UART0 transmit
+    Uart0Tx = 12,
+    /// PL011 UART0 receive
+    Uart0Rx = 14,
+    /// BCM2711 PL011 UART3 transmit
+    #[cfg(feature = "bsp_rpi4")]
+    Uart3Tx = 19,
+    /// BCM2711 PL011 UART3 receive
+    #[cfg(feature = "bsp_rpi4")]
+    Uart3Rx = 20,
+    /// BCM2711 PL011 UART5 transmit
+    #[cfg(feature = "bsp_rpi4")]
+    Uart5Tx = 21,
+    /// BCM2711 PL011 UART5 receive
+    #[cfg(feature = "bsp_rpi4")]
+    Uart5Rx = 22,
+    /// BCM2711 PL011 UART2 transmit
+    #[cfg(feature = "bsp_rpi4")]
+    Uart2Tx = 25,
+    /// BCM2711 PL011 UART2 receive
+    #[cfg(feature = "bsp_rpi4")]
+    Uart2Rx = 26,
+    /// BCM2711 PL011 UART4 transmit
+    #[cfg(feature = "bsp_rpi4")]
+    Uart4Tx = 27,
+    /// BCM2711 PL011 UART4 receive
+    #[cfg(feature = "bsp_rpi4")]
+    Uart4Rx = 28,
+}
+
+/// A channel the loader may use, and its interrupt
+#[derive(Copy, Clone)]
+pub struct DmaChannelConfig {
+    pub index: usize,
+    pub irq_number: IRQNumber,
+}
+
+/// A claimed channel. Deliberately not `Copy`, so a claim can only be released once.
+pub struct DmaChannel {
+    index: usize,
+}
+
+pub struct Dma {
+    registers: NullLock<Registers>,
+
+    /// Channels the BSP lets us use
+    channels: &'static [DmaChannelConfig],
+
+    /// Bus address of RAM address 0, the DMA engine's view of RAM
+    ram_bus_alias: u32,
+
+    /// Who claimed which channel
+    owners: NullLock<[Option<&'static str>; NUM_CHANNELS]>,
+
+    /// One control block per channel, read by the DMA engine while the channel runs
+    control_blocks: UnsafeCell<[ControlBlock; NUM_CHANNELS]>,
+
+    /// Transfers ended on each channel since it was started, counted by the IRQ handler
+    ends: [AtomicUsize; NUM_CHANNELS],
+
+    /// Set once the completion interrupts are enabled and the IRQ handler registered
+    irq_enabled: AtomicBool,
+}
+
+// The control blocks are only touched while their channel is claimed and idle.
+unsafe impl Sync for Dma {}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(pl011)]
+impl Dreq {
+    /// The (transmit, receive) DREQs of PL011 UARTn, `None` if it has none
+    pub const fn pl011(uart: usize) -> Option<(Self, Self)> {
+        match uart {
+            0 => Some((Self::Uart0Tx, Self::Uart0Rx)),
+            #[cfg(feature = "bsp_rpi4")]
+            2 => Some((Self::Uart2Tx, Self::Uart2Rx)),
+            #[cfg(feature = "bsp_rpi4")]
+            3 => Some((Self::Uart3Tx, Self::Uart3Rx)),
+            #[cfg(feature = "bsp_rpi4")]
+            4 => Some((Self::Uart4Tx, Self::Uart4Rx)),
+            #[cfg(feature = "bsp_rpi4")]
+            5 => Some((Self::Uart5Tx, Self::Uart5Rx)),
+            _ => None,
+        }
+    }
+}
+
+impl Dma {
+    /// What `compatible()` returns, for drivers depending on it
+    pub const COMPATIBLE: &'static str = "BCM DMA Controller";
+
+    /// Create an instance
+    ///
+    /// # Safety
+    ///
+    /// - Provide correct MMIO start address, and channels the firmware doesn't use
+    pub const unsafe fn new(
+        mmio_start_addr: usize,
+        channels: &'static [DmaChannelConfig],
+        ram_bus_alias: u32,
+    ) -> Self {
+        const NO_ENDS: AtomicUsize = AtomicUsize::new(0);
+        const EMPTY_CB: ControlBlock = ControlBlock {
+            ti: 0,
+            source_ad: 0,
+            dest_ad: 0,
+            txfr_len: 0,
+            stride: 0,
+            nextconbk: 0,
+            _reserved: [0; 2],
+        };
+
+        Self {
+            registers: NullLock::new(Registers::new(mmio_start_addr)),
+            channels,
+            ram_bus_alias,
+            owners: NullLock::new([None; NUM_CHANNELS]),
+            control_blocks: UnsafeCell::new([EMPTY_CB; NUM_CHANNELS]),
+            ends: [NO_ENDS; NUM_CHANNELS],
+            irq_enabled: AtomicBool::new(false),
+        }
+    }
+
+    /// Claim a free channel for `owner`
+    pub fn claim_channel(&self, owner: &'static str) -> Result<DmaChannel, &'static str> {
+        self.owners.lock(|owners| {
+            let config = self
+                .channels
+                .iter()
+                .find(|config| owners[config.index].is_none())
+                .ok_or("no free DMA channel")?;
+
+            owners[config.index] = Some(owner);
+            Ok(DmaChannel {
+                index: config.index,
+            })
+        })
+    }
+
+    /// Stop a channel and give it back
+    pub fn release_channel(&self, channel: DmaChannel) {
+        self.reset_channel(channel.index);
+        self.owners.lock(|owners| owners[channel.index] = None);
+    }
+
+    /// Stop every channel the loader may use, e.g. before handing the board to an image that
+    /// knows nothing about them. Claims stay as they are.
+    pub fn stop_all(&self) {
+        for config in self.channels {
+            self.reset_channel(config.index);
+        }
+    }
+
+    /// Copy `src` to `dst`, which must be the same length, and wait for it.
+    ///
+    /// Both have to be word aligned. Fails without copying anything if they aren't, or if no
+    /// channel is free, so the caller can fall back to copying them itself. The core mustn't write
+    /// to the cache lines `dst` shares with other data until it returns.
+    pub fn memcpy(&self, dst: &mut [u8], src: &[u8]) -> Result<(), &'static str> {
+        if dst.len() != src.len() {
+            return Err("source and destination lengths differ");
+        }
+        if (dst.as_ptr() as usize | src.as_ptr() as usize | src.len()) % 4 != 0 {
+            return Err("not word aligned");
+        }
+
+        let channel = self.claim_channel("memcpy")?;
+
+        // the DMA engine reads RAM, and writes it under cache lines the core may hold
+        memory::clean_dcache_range(src.as_ptr() as usize, src.len());
+        memory::clean_invalidate_dcache_range(dst.as_ptr() as usize, dst.len());
+
+        let mut result = Ok(());
+        for (dst, src) in dst.chunks_mut(MAX_TRANSFER_LEN).zip(src.chunks(MAX_TRANSFER_LEN)) {
+            let cb = ControlBlock {
+                ti: (TI::SRC_INC::SET
+                    + TI::DEST_INC::SET
+                    + TI::BURST_LENGTH.val(3)
+                    + TI::WAIT_RESP::SET
+                    + TI::INTEN::SET)
+                    .value,
+                source_ad: self.ram_bus_address(src.as_ptr() as usize),
+                dest_ad: self.ram_bus_address(dst.as_mut_ptr() as usize),
+                txfr_len: src.len() as u32,
+                stride: 0,
+                nextconbk: 0,
+                _reserved: [0; 2],
+            };
+
+            self.start(&channel, cb);
+            result = self.wait(&channel, Deadline::after(MEMCPY_TIMEOUT));
+            if result.is_err() {
+                break;
+            }
+        }
+        self.release_channel(channel);
+
+        // lines speculatively fetched during the transfer are stale
+        memory::invalidate_dcache_range(dst.as_ptr() as usize, dst.len());
+
+        result
+    }
+
+    /// Keep filling `ring` with the words a peripheral delivers, paced by its `dreq`, wrapping
+    /// around at the end until the channel is released. [`Dma::ring_position`] tells how far it
+    /// got.
+    ///
+    /// # Safety
+    ///
+    /// - `ring` must stay valid until the channel is released.
+    #[cfg(pl011)]
+    pub unsafe fn start_ring_from_peripheral(
+        &self,
+        channel: &DmaChannel,
+        dreq: Dreq,
+        src_bus_addr: u32,
+        ring: &[AtomicU32],
+    ) {
+        // the control block points back at itself: when the ring is full, start over
+        let cb_bus_addr = self.ram_bus_address(self.control_block(channel.index) as usize);
+
+        // an interrupt at the end of each pass counts them, see ring_position()
+        let cb = ControlBlock {
+            ti: (TI::PERMAP.val(dreq as u32)
+                + TI::SRC_DREQ::SET
+                + TI::DEST_INC::SET
+                + TI::WAIT_RESP::SET
+                + TI::INTEN::SET)
+                .value,
+            source_ad: src_bus_addr,
+            dest_ad: self.ram_bus_address(ring.as_ptr() as usize),
+            txfr_len: (ring.len() * 4) as u32,
+            stride: 0,
+            nextconbk: cb_bus_addr,
+            _reserved: [0; 2],
+        };
+
+        self.start(channel, cb);
+    }
+
+    /// How many bytes a ring started with [`Dma::start_ring_from_peripheral`] has received: its
+    /// whole passes, and how far into the current one the channel got.
+    ///
+    /// The interrupt counting a pass comes a little after the pass ended, meanwhile this is a
+    /// whole ring short.
+    #[cfg(pl011)]
+    pub fn ring_position(&self, channel: &DmaChannel) -> usize {
+        let cb = unsafe { *self.control_block(channel.index) };
+
+        loop {
+            self.poll_ends(channel.index);
+            let passes = self.ends[channel.index].load(Ordering::Acquire);
+
+            let current = self.registers.lock(|regs| regs.CHANNEL[channel.index].DEST_AD.get());
+            let offset = current.wrapping_sub(cb.dest_ad) % cb.txfr_len;
+
+            // a pass counted in between, the offset may belong to either
+            if self.ends[channel.index].load(Ordering::Acquire) == passes {
+                return passes * cb.txfr_len as usize + offset as usize;
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl Dma {
+    fn ram_bus_address(&self, addr: usize) -> u32 {
+        addr as u32 | self.ram_bus_alias
+    }
+
+    fn control_block(&self, index: usize) -> *mut ControlBlock {
+        unsafe { &mut (*self.control_blocks.get())[index] as *mut _ }
+    }
+
+    /// Load `cb` into the channel's control block and start it
+    fn start(&self, channel: &DmaChannel, cb: ControlBlock) {
+        let cb_ptr = self.control_block(channel.index);
+
+        // the channel is idle, the DMA engine doesn't look at its control block
+        unsafe { core::ptr::write_volatile(cb_ptr, cb) };
+        memory::clean_dcache_range(cb_ptr as usize, core::mem::size_of::<ControlBlock>());
+        self.ends[channel.index].store(0, Ordering::Release);
+
+        self.registers.lock(|regs| {
+            let ch = &regs.CHANNEL[channel.index];
+
+            ch.CONBLK_AD.set(self.ram_bus_address(cb_ptr as usize));
+            ch.CS.write(
+                CS::WAIT_FOR_OUTSTANDING_WRITES::SET
+                    + CS::PANIC_PRIORITY.val(15)
+                    + CS::PRIORITY.val(8)
+                    + CS::ACTIVE::SET,
+            );
+        });
+    }
+
+    /// Wait for a transfer started with INTEN to end
+    fn wait(&self, channel: &DmaChannel, deadline: Deadline) -> Result<(), &'static str> {
+        loop {
+            self.poll_ends(channel.index);
+
+            if self.ends[channel.index].load(Ordering::Acquire) != 0 {
+                return Ok(());
+            }
+
+            if self.registers.lock(|regs| regs.CHANNEL[channel.index].CS.is_set(CS::ERROR)) {
+                return Err("DMA transfer failed");
+            }
+            if deadline.has_passed() {
+                return Err("DMA transfer timed out");
+            }
+
+            cpu::nop();
+        }
+    }
+
+    /// Nobody else notices the end of a transfer if the IRQ handler can't run
+    fn poll_ends(&self, index: usize) {
+        if !self.irq_enabled.load(Ordering::Acquire)
+            || exception::asynchronous::is_local_irq_masked()
+        {
+            self.complete(index);
+        }
+    }
+
+    /// Acknowledge the end of a channel's transfer, if it ended
+    fn complete(&self, index: usize) {
+        let ended = self.registers.lock(|regs| {
+            let cs = &regs.CHANNEL[index].CS;
+
+            if !cs.is_set(CS::INT) {
+                return false;
+            }
+            // write 1 to clear, keep ACTIVE etc. as they are
+            cs.modify(CS::INT::SET + CS::END::SET);
+            true
+        });
+
+        // the IRQ handler, or the one core polling while it can't run
+        if ended {
+            let ends = self.ends[index].load(Ordering::Relaxed);
+            self.ends[index].store(ends + 1, Ordering::Release);
+        }
+    }
+
+    fn reset_channel(&self, index: usize) {
+        self.registers.lock(|regs| {
+            let ch = &regs.CHANNEL[index];
+
+            ch.CS.write(CS::RESET::SET);
+            // clear any error the channel latched
+            ch.DEBUG.set(ch.DEBUG.get());
+        });
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl driver::interface::DeviceDriver for Dma {
+    fn compatible(&self) -> &'static str {
+        Self::COMPATIBLE
+    }
+
+    fn init(&self) -> Result<(), &'static str> {
+        for config in self.channels {
+            if config.index >= NUM_CHANNELS {
+                return Err("only the full DMA channels (0-6) are supported");
+            }
+
+            self.registers.lock(|regs| regs.ENABLE.set(regs.ENABLE.get() | 1 << config.index));
+            self.reset_channel(config.index);
+        }
+
+        Ok(())
+    }
+
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        use exception::asynchronous::{interface::IRQManager, irq_manager, IRQDescriptor};
+
+        for config in self.channels {
+            let descriptor = IRQDescriptor {
+                name: "BCM DMA Controller",
+                handler: self,
+            };
+
+            irq_manager().register_handler(config.irq_number, descriptor)?;
+            irq_manager().enable(config.irq_number);
+        }
+        self.irq_enabled.store(true, Ordering::Release);
+
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        self.stop_all();
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQHandler for Dma {
+    fn handle(&self) -> Result<(), &'static str> {
+        let pending = self.registers.lock(|regs| regs.INT_STATUS.get());
+
+        for config in self.channels {
+            if pending & (1 << config.index) != 0 {
+                self.complete(config.index);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -11,9 +11,9 @@ use crate::{
 };
 
 use tock_registers::{
-    interfaces::{ReadWriteable, Writeable},
+    interfaces::{Readable, Writeable},
     register_bitfields, register_structs,
-    registers::ReadWrite,
+    registers::{ReadOnly, ReadWrite, WriteOnly},
 };
 
 //----------------------------------------
@@ -22,32 +22,19 @@ use tock_registers::{
 
 // GPIO registers.
 //
-// Descriptions taken from 
+// Descriptions taken from
 // - https://github.com/raspberrypi/documentation/files/1888662/BCM2837-ARM-Peripherals.-.Revised.-.V2-1.pdf -> this is great
+// - https://datasheets.raspberrypi.com/bcm2711/bcm2711-peripherals.pdf (GPIO_PUP_PDN_CNTRL_REGn)
+//
+// Apart from GPPUD, every register is an array of 32 bit registers indexed by pin number:
+// GPFSELn hold 10 pins (3 bits each), GPIO_PUP_PDN_CNTRL_REGn 16 pins (2 bits each) and the rest
+// one bit per pin.
 
 register_bitfields! {
     u32, // 32 bits wide
 
-    // GPIO Function select register 1 (controls pins 14&15 among others)
-    GPFSEL1 [
-        // pin 14 (offset 12)
-        FSEL14 OFFSET(12) NUMBITS(3) [
-            // possible values for the field
-            input = 0b000,
-            output = 0b001,
-            altFunc0 = 0b100 // uart is in mode0
-        ],
-
-        FSEL15 OFFSET(15) NUMBITS(3) [
-            // possible values for the field
-            input = 0b000,
-            output = 0b001,
-            altFunc0 = 0b100 // uart is in mode0
-        ],
-    ],
-
     // GPIO Pull up/down register
-    // used in conjunction with GPPUDCLK0/1/2
+    // used in conjunction with GPPUDCLK0/1
     GPPUD [
         // pin up/down
         PUD OFFSET(0) NUMBITS(2) [
@@ -55,63 +42,118 @@ register_bitfields! {
             pullDown = 0b01,
             pullUp = 0b10
         ]
-    ],
-
-    // GPIO Pull Up/down clock register 0, to control pins 14&15
-    GPPUDCLK0 [
-        // assert clock on pin n
-        PUDCLK14 OFFSET(14) NUMBITS(1) [
-            noEffect = 0b0,
-            assertClock = 0b1
-        ],
-
-        PUDCLK15 OFFSET(15) NUMBITS(1) [
-            noEffect = 0b0,
-            assertClock = 0b1
-        ]
-    ],
-
-    /// GPIO Pull-up / Pull-down Register 0
-    ///
-    /// BCM2711 only.
-    GPIO_PUP_PDN_CNTRL_REG0 [
-        /// Pin 15
-        GPIO_PUP_PDN_CNTRL15 OFFSET(30) NUMBITS(2) [
-            NoResistor = 0b00,
-            PullUp = 0b01
-        ],
-
-        /// Pin 14
-        GPIO_PUP_PDN_CNTRL14 OFFSET(28) NUMBITS(2) [
-            NoResistor = 0b00,
-            PullUp = 0b01
-        ]
     ]
 }
 
 register_structs! {
     #[allow(non_snake_case)]
     RegisterBlock {
-        (0x00 => _reserved1),
-        (0x04 => GPFSEL1: ReadWrite<u32, GPFSEL1::Register>),
-        (0x08 => _reserved2),
+        // Function select
+        (0x00 => GPFSEL: [ReadWrite<u32>; 6]),
+        (0x18 => _reserved1),
+        // Output set / clear, writing 0 has no effect
+        (0x1C => GPSET: [WriteOnly<u32>; 2]),
+        (0x24 => _reserved2),
+        (0x28 => GPCLR: [WriteOnly<u32>; 2]),
+        (0x30 => _reserved3),
+        // Pin level
+        (0x34 => GPLEV: [ReadOnly<u32>; 2]),
+        (0x3C => _reserved4),
+        // Event detect status, write 1 to clear
+        (0x40 => GPEDS: [ReadWrite<u32>; 2]),
+        (0x48 => _reserved5),
+        // Rising / falling edge, high / low level and async rising / falling edge detect enable
+        (0x4C => GPREN: [ReadWrite<u32>; 2]),
+        (0x54 => _reserved6),
+        (0x58 => GPFEN: [ReadWrite<u32>; 2]),
+        (0x60 => _reserved7),
+        (0x64 => GPHEN: [ReadWrite<u32>; 2]),
+        (0x6C => _reserved8),
+        (0x70 => GPLEN: [ReadWrite<u32>; 2]),
+        (0x78 => _reserved9),
+        (0x7C => GPAREN: [ReadWrite<u32>; 2]),
+        (0x84 => _reserved10),
+        (0x88 => GPAFEN: [ReadWrite<u32>; 2]),
+        (0x90 => _reserved11),
+        // BCM2837 only: pull up/down control and clock
         (0x94 => GPPUD: ReadWrite<u32, GPPUD::Register>),
-        (0x98 => GPPUDCLK0: ReadWrite<u32, GPPUDCLK0::Register>),
-        (0x9C => _reserved3),
-        (0xE4 => GPIO_PUP_PDN_CNTRL_REG0: ReadWrite<u32, GPIO_PUP_PDN_CNTRL_REG0::Register>),
-        (0xE8 => @END),
+        (0x98 => GPPUDCLK: [ReadWrite<u32>; 2]),
+        (0xA0 => _reserved12),
+        // BCM2711 only: pull up/down control
+        (0xE4 => GPIO_PUP_PDN_CNTRL_REG: [ReadWrite<u32>; 4]),
+        (0xF4 => @END),
     }
 }
 
 // abtracts the register calling
 type Registers = MMIODerefWrapper<RegisterBlock>;
 
+/// Index of the one-bit-per-pin register holding `pin`, and the pin's mask in it
+#[inline(always)]
+fn bank_and_mask(pin: u32) -> (usize, u32) {
+    ((pin / 32) as usize, 1 << (pin % 32))
+}
+
 //----------------------------------------
 // public stuff
 //----------------------------------------
 
+/// Number of GPIO pins (the BCM2711 adds pins 54-57 to bank 2)
+#[cfg(feature = "bsp_rpi3")]
+pub const NUM_PINS: u32 = 54;
+
+#[cfg(feature = "bsp_rpi4")]
+pub const NUM_PINS: u32 = 58;
+
+/// Pin function, encoded as in the GPFSELn registers
+#[derive(Copy, Clone, PartialEq)]
+pub enum PinFunction {
+    Input = 0b000,
+    Output = 0b001,
+    Alt0 = 0b100,
+    Alt1 = 0b101,
+    Alt2 = 0b110,
+    Alt3 = 0b111,
+    Alt4 = 0b011,
+    Alt5 = 0b010,
+}
+
+/// Pull resistor state of a pin
+#[derive(Copy, Clone, PartialEq)]
+pub enum Pull {
+    Off,
+    Down,
+    Up,
+}
+
+/// Events the GPIO block can detect on a pin (and raise the GPIO interrupt for)
+#[derive(Copy, Clone, PartialEq)]
+pub enum Event {
+    /// Synchronous rising edge (sampled with the system clock)
+    RisingEdge,
+    /// Synchronous falling edge
+    FallingEdge,
+    /// Pin is high
+    HighLevel,
+    /// Pin is low
+    LowLevel,
+    /// Asynchronous rising edge, for very short pulses
+    AsyncRisingEdge,
+    /// Asynchronous falling edge
+    AsyncFallingEdge,
+}
+
+/// TX / RX pin pair of a UART and the alt function that routes the UART to them
+pub struct UartPins {
+    pub tx: u32,
+    pub rx: u32,
+    pub function: PinFunction,
+}
+
 pub struct GPIOInner {
     registers: Registers,
+    // which driver owns each pin, if any
+    owners: [Option<&'static str>; NUM_PINS as usize],
 }
 
 // Export the inner part for panic to use.
@@ -125,7 +167,9 @@ pub struct GPIO {
     inner: NullLock<GPIOInner>,
 }
 
-// GPIO inner implementations
+// GPIO inner implementations.
+// A pin is claimed by its owner before anything else, every change to it after that names the
+// owner, so a driver can't reconfigure another one's pins.
 impl GPIOInner {
     /// Create an GPIOInner instance
     /// # Safety
@@ -133,13 +177,121 @@ impl GPIOInner {
     pub const unsafe fn new(mmio_start_addr: usize) -> Self {
         Self {
             registers: Registers::new(mmio_start_addr),
+            owners: [None; NUM_PINS as usize],
         }
     }
 
-    /// Disable pull-up/down on pins 14 and 15
+    /// Claim a pin for `owner`. Fails if the pin doesn't exist or another driver owns it.
+    /// Claiming a pin twice for the same owner is fine.
+    pub fn claim(&mut self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        let slot = self
+            .owners
+            .get_mut(pin as usize)
+            .ok_or("GPIO pin out of range")?;
+
+        match slot {
+            Some(current) if *current != owner => Err("GPIO pin already claimed by another driver"),
+            _ => {
+                *slot = Some(owner);
+                Ok(())
+            }
+        }
+    }
+
+    /// Give back a pin claimed by `owner`. The pin stays configured as it is.
+    pub fn release(&mut self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        self.check_owner(pin, owner)?;
+
+        self.owners[pin as usize] = None;
+        Ok(())
+    }
+
+    /// Who claimed a pin, if anyone
+    pub fn owner(&self, pin: u32) -> Result<Option<&'static str>, &'static str> {
+        self.owners.get(pin as usize).copied().ok_or("GPIO pin out of range")
+    }
+
+    /// Fails unless the pin exists and `owner` claimed it
+    fn check_owner(&self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        match self.owners.get(pin as usize).ok_or("GPIO pin out of range")? {
+            Some(current) if *current == owner => Ok(()),
+            Some(_) => Err("GPIO pin claimed by another driver"),
+            None => Err("GPIO pin not claimed"),
+        }
+    }
+
+    /// Select the function of a pin claimed by `owner`
+    pub fn set_function(
+        &mut self,
+        pin: u32,
+        function: PinFunction,
+        owner: &'static str,
+    ) -> Result<(), &'static str> {
+        self.check_owner(pin, owner)?;
+
+        self.write_function(pin, function as u32);
+        Ok(())
+    }
+
+    /// The function a pin is set to
+    pub fn function(&self, pin: u32) -> Result<PinFunction, &'static str> {
+        if pin >= NUM_PINS {
+            return Err("GPIO pin out of range");
+        }
+
+        Ok(match self.read_function(pin) {
+            0b000 => PinFunction::Input,
+            0b001 => PinFunction::Output,
+            0b100 => PinFunction::Alt0,
+            0b101 => PinFunction::Alt1,
+            0b110 => PinFunction::Alt2,
+            0b111 => PinFunction::Alt3,
+            0b011 => PinFunction::Alt4,
+            // 0b010, the last three bit value
+            _ => PinFunction::Alt5,
+        })
+    }
+
+    fn read_function(&self, pin: u32) -> u32 {
+        (self.registers.GPFSEL[(pin / 10) as usize].get() >> ((pin % 10) * 3)) & 0b111
+    }
+
+    fn write_function(&mut self, pin: u32, function: u32) {
+        let reg = &self.registers.GPFSEL[(pin / 10) as usize];
+        let shift = (pin % 10) * 3;
+
+        reg.set((reg.get() & !(0b111 << shift)) | (function << shift));
+    }
+
+    /// Drive an output pin claimed by `owner` high or low
+    pub fn set_level(&mut self, pin: u32, high: bool, owner: &'static str) -> Result<(), &'static str> {
+        self.check_owner(pin, owner)?;
+
+        let (bank, mask) = bank_and_mask(pin);
+        if high {
+            self.registers.GPSET[bank].set(mask);
+        } else {
+            self.registers.GPCLR[bank].set(mask);
+        }
+        Ok(())
+    }
+
+    /// Read the current level of a pin
+    pub fn read_level(&self, pin: u32) -> Result<bool, &'static str> {
+        if pin >= NUM_PINS {
+            return Err("GPIO pin out of range");
+        }
+
+        let (bank, mask) = bank_and_mask(pin);
+        Ok(self.registers.GPLEV[bank].get() & mask != 0)
+    }
+
+    /// Set the pull-up/down state of the given pins, all claimed by `owner`
     #[cfg(feature = "bsp_rpi3")]
-    fn disable_pud_14_15_bcm2837(&mut self) {
-        use crate::cpu;
+    pub fn set_pull(&mut self, pins: &[u32], pull: Pull, owner: &'static str) -> Result<(), &'static str> {
+        use crate::{time, time::interface::TimeManager};
+        use core::time::Duration;
+
         // 1. Write to GPPUD to set the required control signal (i.e. Pull-up or Pull-Down or neither
         // to remove the current Pull-up/down)
         // 2. Wait 150 cycles – this provides the required set-up time for the control signal
@@ -150,58 +302,180 @@ impl GPIOInner {
         // 5. Write to GPPUD to remove the control signal
         // 6. Write to GPPUDCLK0/1 to remove the clock
 
-        // Make an educated guess for a good delay value (Sequence described in the BCM2837
-        // peripherals PDF).
-        //
-        // - According to Wikipedia, the fastest Pi3 clocks around 1.4 GHz.
-        // - The Linux 2837 GPIO driver waits 1 µs between the steps.
-        //
-        // So lets try to be on the safe side and default to 2000 cycles, which would equal 1 µs
-        // would the CPU be clocked at 2 GHz.
-        const DELAY: usize = 2000;
-
-        // turn off pull up/down
-        self.registers.GPPUD.write(GPPUD::PUD::off);
+        // 150 cycles of the GPIO clock. Like the Linux 2837 GPIO driver, wait 1 µs between the
+        // steps, which covers it with a good margin.
+        const DELAY: Duration = Duration::from_micros(1);
+
+        let mut clocks = [0u32; 2];
+        for pin in pins {
+            self.check_owner(*pin, owner)?;
+
+            let (bank, mask) = bank_and_mask(*pin);
+            clocks[bank] |= mask;
+        }
+
+        // set the control signal
+        self.registers.GPPUD.write(match pull {
+            Pull::Off => GPPUD::PUD::off,
+            Pull::Down => GPPUD::PUD::pullDown,
+            Pull::Up => GPPUD::PUD::pullUp,
+        });
         // wait
-        cpu::spin_for_cycles(DELAY);
-        // assert clock on pins 14 and 15
-        self.registers
-            .GPPUDCLK0
-            .write(GPPUDCLK0::PUDCLK14::assertClock + GPPUDCLK0::PUDCLK15::assertClock);
+        time::time_manager().spin_for(DELAY);
+        // assert clock on the requested pins
+        self.registers.GPPUDCLK[0].set(clocks[0]);
+        self.registers.GPPUDCLK[1].set(clocks[1]);
         // wait
-        cpu::spin_for_cycles(DELAY);
-        // turn off pull up/down
+        time::time_manager().spin_for(DELAY);
+        // remove the control signal
         self.registers.GPPUD.write(GPPUD::PUD::off);
-        // write to GPPUDCLK0 to remove clock
-        self.registers.GPPUDCLK0.set(0);
+        // write to GPPUDCLK0/1 to remove clock
+        self.registers.GPPUDCLK[0].set(0);
+        self.registers.GPPUDCLK[1].set(0);
+        Ok(())
     }
-    /// Disable pull-up/down on pins 14 and 15. - copied, not tested
+
+    /// Set the pull-up/down state of the given pins, all claimed by `owner`.
+    /// The BCM2711 has a dedicated register field per pin, no clocking needed.
     #[cfg(feature = "bsp_rpi4")]
-    fn disable_pud_14_15_bcm2711(&mut self) {
-        self.registers.GPIO_PUP_PDN_CNTRL_REG0.write(
-            GPIO_PUP_PDN_CNTRL_REG0::GPIO_PUP_PDN_CNTRL15::PullUp
-                + GPIO_PUP_PDN_CNTRL_REG0::GPIO_PUP_PDN_CNTRL14::PullUp,
-        );
-    }
-
-    /// Choose alt function 0 for pins 14, 15
-    /// and disaple pull up/down
-    /// TX - pin 15
-    /// RX - pin 14
-    pub fn init_pl011_uart_pins(&mut self) {
-        self.registers
-            .GPFSEL1
-            .modify(GPFSEL1::FSEL14::altFunc0 + GPFSEL1::FSEL15::altFunc0);
+    pub fn set_pull(&mut self, pins: &[u32], pull: Pull, owner: &'static str) -> Result<(), &'static str> {
+        let value = match pull {
+            Pull::Off => 0b00,
+            Pull::Up => 0b01,
+            Pull::Down => 0b10,
+        };
+
+        for pin in pins {
+            self.check_owner(*pin, owner)?;
+        }
+
+        for pin in pins {
+            let reg = &self.registers.GPIO_PUP_PDN_CNTRL_REG[(pin / 16) as usize];
+            let shift = (pin % 16) * 2;
+
+            reg.set((reg.get() & !(0b11 << shift)) | (value << shift));
+        }
+        Ok(())
+    }
 
+    /// The detect-enable register bank for an event
+    fn event_registers(&self, event: Event) -> &[ReadWrite<u32>; 2] {
+        match event {
+            Event::RisingEdge => &self.registers.GPREN,
+            Event::FallingEdge => &self.registers.GPFEN,
+            Event::HighLevel => &self.registers.GPHEN,
+            Event::LowLevel => &self.registers.GPLEN,
+            Event::AsyncRisingEdge => &self.registers.GPAREN,
+            Event::AsyncFallingEdge => &self.registers.GPAFEN,
+        }
+    }
+
+    /// Enable or disable detection of `event` on a pin claimed by `owner`
+    pub fn set_event_detect(
+        &mut self,
+        pin: u32,
+        event: Event,
+        enable: bool,
+        owner: &'static str,
+    ) -> Result<(), &'static str> {
+        self.check_owner(pin, owner)?;
+
+        let (bank, mask) = bank_and_mask(pin);
+        let reg = &self.event_registers(event)[bank];
+
+        if enable {
+            reg.set(reg.get() | mask);
+        } else {
+            reg.set(reg.get() & !mask);
+        }
+        Ok(())
+    }
+
+    /// True if an enabled event was detected on the pin since its status was last cleared
+    pub fn event_detected(&self, pin: u32) -> Result<bool, &'static str> {
+        if pin >= NUM_PINS {
+            return Err("GPIO pin out of range");
+        }
+
+        let (bank, mask) = bank_and_mask(pin);
+        Ok(self.registers.GPEDS[bank].get() & mask != 0)
+    }
+
+    /// Clear the event detect status of a pin claimed by `owner`. Level events keep reasserting as
+    /// long as the level is present.
+    pub fn clear_event(&mut self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        self.check_owner(pin, owner)?;
+
+        let (bank, mask) = bank_and_mask(pin);
+        self.registers.GPEDS[bank].set(mask);
+        Ok(())
+    }
+
+    /// Claim a UART's TX/RX pins for `owner`, route the UART to them and set up their pulls:
+    /// - BCM2837: pull up/down disabled
+    /// - BCM2711: pull-up, so RX idles high with no cable attached
+    pub fn init_uart_pins(&mut self, pins: &UartPins, owner: &'static str) -> Result<(), &'static str> {
         #[cfg(feature = "bsp_rpi3")]
-        self.disable_pud_14_15_bcm2837();
+        const UART_PIN_PULL: Pull = Pull::Off;
 
         #[cfg(feature = "bsp_rpi4")]
-        self.disable_pud_14_15_bcm2711();
+        const UART_PIN_PULL: Pull = Pull::Up;
+
+        self.claim(pins.tx, owner)?;
+        self.claim(pins.rx, owner)?;
+
+        self.set_function(pins.tx, pins.function, owner)?;
+        self.set_function(pins.rx, pins.function, owner)?;
+
+        self.set_pull(&[pins.tx, pins.rx], UART_PIN_PULL, owner)
+    }
+
+    /// Claim a pin for `owner` and configure it as an input with the given pull
+    pub fn init_input_pin(&mut self, pin: u32, pull: Pull, owner: &'static str) -> Result<(), &'static str> {
+        self.claim(pin, owner)?;
+
+        self.set_function(pin, PinFunction::Input, owner)?;
+        self.set_pull(&[pin], pull, owner)
+    }
+
+    /// Claim a pin for `owner` and configure it as an output driving `high`
+    pub fn init_output_pin(&mut self, pin: u32, high: bool, owner: &'static str) -> Result<(), &'static str> {
+        self.claim(pin, owner)?;
+
+        // set the level first so the pin doesn't glitch when switching to output
+        self.set_level(pin, high, owner)?;
+        self.set_function(pin, PinFunction::Output, owner)
+    }
+
+    /// Stop detecting events on every pin, clear the events detected, and turn the claimed
+    /// outputs (the status LED among them) back into inputs. Pins routed to a peripheral stay, the
+    /// image may keep using it.
+    pub fn quiesce(&mut self) {
+        for bank in 0..2 {
+            self.registers.GPREN[bank].set(0);
+            self.registers.GPFEN[bank].set(0);
+            self.registers.GPHEN[bank].set(0);
+            self.registers.GPLEN[bank].set(0);
+            self.registers.GPAREN[bank].set(0);
+            self.registers.GPAFEN[bank].set(0);
+            self.registers.GPEDS[bank].set(u32::MAX);
+        }
+
+        for pin in 0..NUM_PINS {
+            let claimed = self.owners[pin as usize].is_some();
+
+            if claimed && self.read_function(pin) == PinFunction::Output as u32 {
+                self.write_function(pin, PinFunction::Input as u32);
+            }
+        }
     }
 }
 
+// Same as the inner methods, but with wrapping lock.
 impl GPIO {
+    /// What `compatible()` returns, for drivers depending on it
+    pub const COMPATIBLE: &'static str = "GPIO Device Driver";
+
     /// Create an instance of GPIO device driver
     /// # Safety
     /// - User must ensure validity of the mmio start address
@@ -211,10 +485,85 @@ impl GPIO {
         }
     }
 
-    /// init gpio uart pins
-    /// same as the inner method, but with wrapping lock.
-    pub fn init_gpio_uart_pins(&self) {
-        self.inner.lock(|inner| inner.init_pl011_uart_pins());
+    /// Claim a pin for `owner`
+    pub fn claim(&self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.claim(pin, owner))
+    }
+
+    /// Give back a pin claimed by `owner`
+    pub fn release(&self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.release(pin, owner))
+    }
+
+    /// Who claimed a pin, if anyone
+    pub fn owner(&self, pin: u32) -> Result<Option<&'static str>, &'static str> {
+        self.inner.lock(|inner| inner.owner(pin))
+    }
+
+    /// Select the function of a pin claimed by `owner`
+    pub fn set_function(
+        &self,
+        pin: u32,
+        function: PinFunction,
+        owner: &'static str,
+    ) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.set_function(pin, function, owner))
+    }
+
+    /// The function a pin is set to
+    pub fn function(&self, pin: u32) -> Result<PinFunction, &'static str> {
+        self.inner.lock(|inner| inner.function(pin))
+    }
+
+    /// Set the pull-up/down state of the given pins, all claimed by `owner`
+    pub fn set_pull(&self, pins: &[u32], pull: Pull, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.set_pull(pins, pull, owner))
+    }
+
+    /// Drive an output pin claimed by `owner` high or low
+    pub fn set_level(&self, pin: u32, high: bool, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.set_level(pin, high, owner))
+    }
+
+    /// Read the current level of a pin
+    pub fn read_level(&self, pin: u32) -> Result<bool, &'static str> {
+        self.inner.lock(|inner| inner.read_level(pin))
+    }
+
+    /// Enable or disable detection of `event` on a pin claimed by `owner`
+    pub fn set_event_detect(
+        &self,
+        pin: u32,
+        event: Event,
+        enable: bool,
+        owner: &'static str,
+    ) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.set_event_detect(pin, event, enable, owner))
+    }
+
+    /// True if an enabled event was detected on the pin since its status was last cleared
+    pub fn event_detected(&self, pin: u32) -> Result<bool, &'static str> {
+        self.inner.lock(|inner| inner.event_detected(pin))
+    }
+
+    /// Clear the event detect status of a pin claimed by `owner`
+    pub fn clear_event(&self, pin: u32, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.clear_event(pin, owner))
+    }
+
+    /// Claim and route a UART's pins
+    pub fn init_uart_pins(&self, pins: &UartPins, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.init_uart_pins(pins, owner))
+    }
+
+    /// Claim a pin and configure it as an input with the given pull
+    pub fn init_input_pin(&self, pin: u32, pull: Pull, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.init_input_pin(pin, pull, owner))
+    }
+
+    /// Claim a pin and configure it as an output driving `high`
+    pub fn init_output_pin(&self, pin: u32, high: bool, owner: &'static str) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.init_output_pin(pin, high, owner))
     }
 }
 
@@ -223,10 +572,16 @@ impl GPIO {
 impl driver::interface::DeviceDriver for GPIO {
     /// Returns identity string of the driver
     fn compatible(&self) -> &'static str {
-        "GPIO Device Driver"
+        Self::COMPATIBLE
     }
   //  fn init(&self) -> Result<(), &'static str> {
         //self.init_gpio_uart_pins();
         //Ok(())
     //}
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.quiesce());
+
+        Ok(())
+    }
 }
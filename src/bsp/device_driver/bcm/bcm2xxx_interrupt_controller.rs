@@ -0,0 +1,157 @@
+/*
+ * File: bcm2xxx_interrupt_controller.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 10:02:55 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Interrupt controller driver for the RPi3: the BCM2836 local interrupt controller (per core:
+//! ARM timers, mailboxes, PMU, and the "GPU" line) in front of the BCM2835 ARMCTRL peripheral
+//! interrupt controller (the VideoCore's 64 peripheral IRQs, e.g. UART, system timer).
+//!
+//! A peripheral IRQ shows up at the local controller as the GPU interrupt, which the firmware
+//! routes to core 0.
+
+mod local_ic;
+mod peripheral_ic;
+
+use crate::{
+    driver,
+    exception::{self, asynchronous::IRQDescriptor},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The controller an IRQ number belongs to, and the number there
+enum Source {
+    Local(LocalIRQ),
+    Peripheral(PeripheralIRQ),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Local (per core) IRQ number, see the local IC's source bits
+pub type LocalIRQ =
+    exception::asynchronous::BoundedUsize<{ InterruptController::MAX_LOCAL_IRQ_NUMBER }>;
+
+/// Peripheral IRQ number, 0-31 in ARMCTRL's bank 1 and 32-63 in bank 2
+pub type PeripheralIRQ =
+    exception::asynchronous::BoundedUsize<{ InterruptController::MAX_PERIPHERAL_IRQ_NUMBER }>;
+
+/// Used for the associated type of trait [`exception::asynchronous::interface::IRQManager`].
+///
+/// 0-63 are the peripheral IRQs, 64-75 the local sources 0-11 of the executing core.
+pub type IRQNumber = exception::asynchronous::BoundedUsize<{ InterruptController::MAX_IRQ_NUMBER }>;
+
+/// Representation of the Interrupt Controller
+pub struct InterruptController {
+    local: local_ic::LocalIC,
+    periph: peripheral_ic::PeripheralIC,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl InterruptController {
+    // the local IC's CORE_IRQ_SOURCE bits 0-11, ARMCTRL's two banks of 32
+    const MAX_LOCAL_IRQ_NUMBER: usize = 11;
+    const MAX_PERIPHERAL_IRQ_NUMBER: usize = 63;
+
+    const NUM_LOCAL_IRQS: usize = Self::MAX_LOCAL_IRQ_NUMBER + 1;
+    const NUM_PERIPHERAL_IRQS: usize = Self::MAX_PERIPHERAL_IRQ_NUMBER + 1;
+
+    // the local sources follow the peripheral IRQs
+    const FIRST_LOCAL_IRQ_NUMBER: usize = Self::NUM_PERIPHERAL_IRQS;
+    const MAX_IRQ_NUMBER: usize = Self::FIRST_LOCAL_IRQ_NUMBER + Self::MAX_LOCAL_IRQ_NUMBER;
+
+    /// Create an instance
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide correct MMIO start addresses
+    pub const unsafe fn new(local_mmio_start_addr: usize, periph_mmio_start_addr: usize) -> Self {
+        Self {
+            local: local_ic::LocalIC::new(local_mmio_start_addr),
+            periph: peripheral_ic::PeripheralIC::new(periph_mmio_start_addr),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl driver::interface::DeviceDriver for InterruptController {
+    fn compatible(&self) -> &'static str {
+        "BCM Interrupt Controller"
+    }
+
+    fn init(&self) -> Result<(), &'static str> {
+        self.local.init();
+        self.periph.init();
+
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        self.local.disable_all();
+        self.periph.disable_all();
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQManager for InterruptController {
+    type IRQNumberType = IRQNumber;
+
+    fn register_handler(
+        &self,
+        irq_number: Self::IRQNumberType,
+        descriptor: IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        match Source::of(irq_number) {
+            Source::Local(irq) => self.local.register_handler(irq, descriptor),
+            Source::Peripheral(irq) => self.periph.register_handler(irq, descriptor),
+        }
+    }
+
+    fn enable(&self, irq_number: Self::IRQNumberType) {
+        match Source::of(irq_number) {
+            Source::Local(irq) => self.local.enable(irq),
+            Source::Peripheral(irq) => self.periph.enable(irq),
+        }
+    }
+
+    fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        // the local controller reports the peripheral IRQs as one source, the GPU interrupt
+        if self.local.handle_pending_irqs(ic) {
+            self.periph.handle_pending_irqs(ic);
+        }
+    }
+
+    fn print_handler(&self) {
+        self.local.print_handler();
+        self.periph.print_handler();
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl Source {
+    fn of(irq_number: IRQNumber) -> Self {
+        match irq_number.get().checked_sub(InterruptController::FIRST_LOCAL_IRQ_NUMBER) {
+            Some(local) => Self::Local(LocalIRQ::new(local)),
+            None => Self::Peripheral(PeripheralIRQ::new(irq_number.get())),
+        }
+    }
+}
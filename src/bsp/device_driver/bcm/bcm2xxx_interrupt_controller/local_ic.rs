@@ -0,0 +1,211 @@
+/*
+ * File: local_ic.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 10:20:37 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BCM2836 local interrupt controller (the "ARM local" block at 0x4000_0000).
+//!
+//! Every core has its own source register, and its own enables for its ARM timers and mailboxes.
+//! Enabling a source enables it on the executing core.
+//!
+//! Descriptions taken from
+//! - https://datasheets.raspberrypi.com/bcm2836/bcm2836-peripherals.pdf (QA7)
+
+use super::{InterruptController, LocalIRQ};
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper,
+    cpu,
+    exception::{self, asynchronous::{IRQDescriptor, IRQHandlers}},
+    println,
+    synchronization::{interface::Mutex, NullLock},
+};
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_structs,
+    registers::{ReadOnly, ReadWrite, WriteOnly},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RegisterBlock {
+        (0x00 => _reserved1),
+        // which core gets the GPU (peripheral) IRQ and FIQ
+        (0x0c => GPU_INT_ROUTING: ReadWrite<u32>),
+        // bit n routes the PMU interrupt of core n to its IRQ line
+        (0x10 => PMU_INT_ROUTING_SET: WriteOnly<u32>),
+        // bit n stops routing the PMU interrupt of core n
+        (0x14 => PMU_INT_ROUTING_CLR: WriteOnly<u32>),
+        (0x18 => _reserved2),
+        // per core: bits 0-3 enable the CNTPS, CNTPNS, CNTHP and CNTV timer IRQs
+        (0x40 => CORE_TIMER_IRQCNTL: [ReadWrite<u32>; 4]),
+        // per core: bits 0-3 enable the IRQs of mailboxes 0-3
+        (0x50 => CORE_MBOX_IRQCNTL: [ReadWrite<u32>; 4]),
+        // per core: pending IRQ sources, see `Source`
+        (0x60 => CORE_IRQ_SOURCE: [ReadOnly<u32>; 4]),
+        (0x70 => @END),
+    }
+}
+
+/// Abstraction for the associated MMIO registers.
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// Source bits of CORE_IRQ_SOURCE
+mod source {
+    pub const FIRST_TIMER: usize = 0;
+    pub const LAST_TIMER: usize = 3;
+    pub const FIRST_MAILBOX: usize = 4;
+    pub const LAST_MAILBOX: usize = 7;
+    pub const GPU: usize = 8;
+    pub const PMU: usize = 9;
+}
+
+type HandlerTable = [IRQHandlers; InterruptController::NUM_LOCAL_IRQS];
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Representation of the local interrupt controller
+pub struct LocalIC {
+    registers: NullLock<Registers>,
+    handler_table: NullLock<HandlerTable>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl LocalIC {
+    /// Create an instance
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO start address
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            registers: NullLock::new(Registers::new(mmio_start_addr)),
+            handler_table: NullLock::new([IRQHandlers::new(); InterruptController::NUM_LOCAL_IRQS]),
+        }
+    }
+
+    /// Route the GPU interrupt (all peripheral IRQs) to the boot core. The firmware does it
+    /// already, but doesn't promise to.
+    pub fn init(&self) {
+        let core = cpu::core_id() as u32;
+
+        self.registers.lock(|regs| {
+            // bits 1:0 pick the IRQ target, bits 3:2 the FIQ target
+            regs.GPU_INT_ROUTING.set((regs.GPU_INT_ROUTING.get() & !0b11) | core);
+        });
+    }
+
+    /// Disable the sources [`Self::enable`] enabled on the executing core
+    pub fn disable_all(&self) {
+        let core = cpu::core_id();
+
+        self.registers.lock(|regs| {
+            regs.CORE_TIMER_IRQCNTL[core].set(0);
+            regs.CORE_MBOX_IRQCNTL[core].set(0);
+            regs.PMU_INT_ROUTING_CLR.set(1 << core);
+        });
+    }
+
+    /// Register a handler
+    pub fn register_handler(
+        &self,
+        irq: LocalIRQ,
+        descriptor: IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        if irq.get() == source::GPU {
+            return Err("Local IRQ 8 is the peripheral interrupt controller's");
+        }
+
+        self.handler_table.lock(|table| table[irq.get()].add(descriptor))
+    }
+
+    /// Enable a source on the executing core. Sources without an enable (the GPU interrupt,
+    /// AXI and local timer) are left alone.
+    pub fn enable(&self, irq: LocalIRQ) {
+        let core = cpu::core_id();
+
+        self.registers.lock(|regs| match irq.get() {
+            n @ source::FIRST_TIMER..=source::LAST_TIMER => {
+                let reg = &regs.CORE_TIMER_IRQCNTL[core];
+                reg.set(reg.get() | (1 << (n - source::FIRST_TIMER)));
+            }
+            n @ source::FIRST_MAILBOX..=source::LAST_MAILBOX => {
+                let reg = &regs.CORE_MBOX_IRQCNTL[core];
+                reg.set(reg.get() | (1 << (n - source::FIRST_MAILBOX)));
+            }
+            source::PMU => regs.PMU_INT_ROUTING_SET.set(1 << core),
+            _ => {}
+        });
+    }
+
+    /// Disable a source on the executing core, the counterpart of [`Self::enable`]. The sources
+    /// without an enable are left alone here too.
+    pub fn disable(&self, irq: LocalIRQ) {
+        let core = cpu::core_id();
+
+        self.registers.lock(|regs| match irq.get() {
+            n @ source::FIRST_TIMER..=source::LAST_TIMER => {
+                let reg = &regs.CORE_TIMER_IRQCNTL[core];
+                reg.set(reg.get() & !(1 << (n - source::FIRST_TIMER)));
+            }
+            n @ source::FIRST_MAILBOX..=source::LAST_MAILBOX => {
+                let reg = &regs.CORE_MBOX_IRQCNTL[core];
+                reg.set(reg.get() & !(1 << (n - source::FIRST_MAILBOX)));
+            }
+            source::PMU => regs.PMU_INT_ROUTING_CLR.set(1 << core),
+            _ => {}
+        });
+    }
+
+    /// Handle the executing core's pending sources. Returns true if the GPU interrupt is
+    /// pending, which is for the peripheral interrupt controller to handle.
+    pub fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) -> bool {
+        let core = cpu::core_id();
+        let pending = self.registers.lock(|regs| regs.CORE_IRQ_SOURCE[core].get());
+
+        self.handler_table.lock(|table| {
+            for (irq, handlers) in table.iter().enumerate() {
+                if irq == source::GPU || pending & (1 << irq) == 0 {
+                    continue;
+                }
+
+                // Nobody asked for it, so it was enabled behind our back (e.g. by the firmware).
+                // Mask it instead of taking it over and over.
+                if handlers.is_empty() {
+                    self.disable(LocalIRQ::new(irq));
+                    println!("Masked local IRQ {}, no handler registered", irq);
+                    continue;
+                }
+
+                // Call the IRQ handlers. Panics on failure.
+                handlers.handle().expect("Error handling IRQ");
+            }
+        });
+
+        pending & (1 << source::GPU) != 0
+    }
+
+    /// Print the registered handlers
+    pub fn print_handler(&self) {
+        self.handler_table.lock(|table| {
+            for (i, handlers) in table.iter().enumerate() {
+                for name in handlers.names() {
+                    println!("      Local {: >3}. {}", i, name);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,194 @@
+/*
+ * File: peripheral_ic.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 10:41:09 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BCM2835 ARMCTRL peripheral interrupt controller.
+//!
+//! Descriptions taken from
+//! - https://github.com/raspberrypi/documentation/files/1888662/BCM2837-ARM-Peripherals.-.Revised.-.V2-1.pdf
+//!   (chapter 7, Interrupts)
+
+use super::{InterruptController, PeripheralIRQ};
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper,
+    exception::{self, asynchronous::{IRQDescriptor, IRQHandlers}},
+    println,
+    synchronization::{interface::Mutex, NullLock},
+};
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_structs,
+    registers::{ReadOnly, WriteOnly},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+register_structs! {
+    #[allow(non_snake_case)]
+    WORegisterBlock {
+        (0x00 => _reserved1),
+        (0x10 => ENABLE_1: WriteOnly<u32>),
+        (0x14 => ENABLE_2: WriteOnly<u32>),
+        (0x18 => _reserved2),
+        (0x1c => DISABLE_1: WriteOnly<u32>),
+        (0x20 => DISABLE_2: WriteOnly<u32>),
+        (0x24 => @END),
+    }
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    RORegisterBlock {
+        (0x00 => _reserved1),
+        (0x04 => PENDING_1: ReadOnly<u32>),
+        (0x08 => PENDING_2: ReadOnly<u32>),
+        (0x0c => @END),
+    }
+}
+
+/// Abstraction for the WriteOnly parts of the associated MMIO registers
+type WriteOnlyRegisters = MMIODerefWrapper<WORegisterBlock>;
+
+/// Abstraction for the ReadOnly parts of the associated MMIO registers
+type ReadOnlyRegisters = MMIODerefWrapper<RORegisterBlock>;
+
+type HandlerTable = [IRQHandlers; InterruptController::NUM_PERIPHERAL_IRQS];
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Representation of the peripheral interrupt controller
+pub struct PeripheralIC {
+    /// Access to write registers is guarded with a lock
+    wo_registers: NullLock<WriteOnlyRegisters>,
+
+    /// Register read access is unguarded
+    ro_registers: ReadOnlyRegisters,
+
+    /// Stores registered IRQ handlers
+    handler_table: NullLock<HandlerTable>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl PeripheralIC {
+    /// Create an instance
+    ///
+    /// # Safety
+    ///
+    /// - The user must ensure to provide a correct MMIO start address
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            wo_registers: NullLock::new(WriteOnlyRegisters::new(mmio_start_addr)),
+            ro_registers: ReadOnlyRegisters::new(mmio_start_addr),
+            handler_table: NullLock::new([IRQHandlers::new(); InterruptController::NUM_PERIPHERAL_IRQS]),
+        }
+    }
+
+    /// Start from a clean slate: nothing enabled until a handler asks for it
+    pub fn init(&self) {
+        self.disable_all();
+    }
+
+    /// Disable all interrupts
+    pub fn disable_all(&self) {
+        self.wo_registers.lock(|regs| {
+            regs.DISABLE_1.set(u32::MAX);
+            regs.DISABLE_2.set(u32::MAX);
+        });
+    }
+
+    /// Register a handler
+    pub fn register_handler(
+        &self,
+        irq: PeripheralIRQ,
+        descriptor: IRQDescriptor,
+    ) -> Result<(), &'static str> {
+        self.handler_table.lock(|table| table[irq.get()].add(descriptor))
+    }
+
+    /// Enable an interrupt
+    pub fn enable(&self, irq: PeripheralIRQ) {
+        self.wo_registers.lock(|regs| {
+            let enable_bit: u32 = 1 << (irq.get() % 32);
+
+            // Writing a 1 to a bit will set the corresponding IRQ enable bit. All other IRQ
+            // enable bits are unaffected. So we don't need read and OR'ing here.
+            if irq.get() < 32 {
+                regs.ENABLE_1.set(enable_bit);
+            } else {
+                regs.ENABLE_2.set(enable_bit);
+            }
+        });
+    }
+
+    /// Disable an interrupt
+    pub fn disable(&self, irq: PeripheralIRQ) {
+        self.wo_registers.lock(|regs| {
+            let disable_bit: u32 = 1 << (irq.get() % 32);
+
+            if irq.get() < 32 {
+                regs.DISABLE_1.set(disable_bit);
+            } else {
+                regs.DISABLE_2.set(disable_bit);
+            }
+        });
+    }
+
+    /// Handle pending interrupts
+    pub fn handle_pending_irqs<'irq_context>(
+        &'irq_context self,
+        _ic: &exception::asynchronous::IRQContext<'irq_context>,
+    ) {
+        let mut pending = self.pending_irqs();
+
+        self.handler_table.lock(|table| {
+            while pending != 0 {
+                let irq = pending.trailing_zeros() as usize;
+                pending &= pending - 1;
+
+                if table[irq].is_empty() {
+                    self.disable(PeripheralIRQ::new(irq));
+                    println!("Masked peripheral IRQ {}, no handler registered", irq);
+                    continue;
+                }
+
+                // Call the IRQ handlers. Panics on failure.
+                table[irq].handle().expect("Error handling IRQ");
+            }
+        });
+    }
+
+    /// Print the registered handlers
+    pub fn print_handler(&self) {
+        self.handler_table.lock(|table| {
+            for (i, handlers) in table.iter().enumerate() {
+                for name in handlers.names() {
+                    println!("      Peripheral {: >3}. {}", i, name);
+                }
+            }
+        });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl PeripheralIC {
+    /// Query the list of pending IRQs, bank 2 in the upper half
+    fn pending_irqs(&self) -> u64 {
+        let pending_1 = u64::from(self.ro_registers.PENDING_1.get());
+        let pending_2 = u64::from(self.ro_registers.PENDING_2.get());
+
+        (pending_2 << 32) | pending_1
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ * File: bcm2xxx_mailbox.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 5:40:13 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! VideoCore mailbox driver (property channel only).
+//!
+//! The firmware running on the VideoCore owns some of the board's hardware, like the GPIO expander
+//! the RPi3's activity LED hangs off. The property interface is how we ask it to do things.
+//!
+//! Descriptions taken from
+//! - https://github.com/raspberrypi/firmware/wiki/Mailboxes
+//! - https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface
+
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper, cpu, driver, memory,
+    synchronization::interface::Mutex,
+    synchronization::NullLock, time::Deadline,
+};
+use core::time::Duration;
+
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_bitfields, register_structs,
+    registers::{ReadOnly, WriteOnly},
+};
+
+//----------------------------------------
+// private stuff
+//----------------------------------------
+
+register_bitfields! {
+    u32, // 32 bit wide
+
+    /// Mailbox status
+    STATUS [
+        /// No space to write a message
+        FULL OFFSET(31) NUMBITS(1) [],
+
+        /// Nothing to read
+        EMPTY OFFSET(30) NUMBITS(1) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    pub RegisterBlock {
+        // mailbox 0: VideoCore -> ARM
+        (0x00 => READ: ReadOnly<u32>),
+        (0x04 => _reserved1),
+        (0x18 => STATUS: ReadOnly<u32, STATUS::Register>),
+        (0x1c => _reserved2),
+        // mailbox 1: ARM -> VideoCore
+        (0x20 => WRITE: WriteOnly<u32>),
+        (0x24 => _reserved3),
+        (0x38 => WRITE_STATUS: ReadOnly<u32, STATUS::Register>),
+        (0x3c => @END),
+    }
+}
+
+// abtracts the register calling
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// Channel of the ARM -> VideoCore property interface
+const CHANNEL_PROPERTY: u32 = 8;
+
+/// Buffer request/response codes
+const REQUEST: u32 = 0;
+const RESPONSE_SUCCESS: u32 = 0x8000_0000;
+
+/// The firmware answers property calls within a few ms, give up on it after this long
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Property tags
+const TAG_SET_GPIO_STATE: u32 = 0x0003_8041;
+const TAG_END: u32 = 0;
+
+/// Message buffer. The low 4 bits of its address carry the channel, so it has to be 16 byte
+/// aligned. Cache line aligned, so cleaning and invalidating it only touches the buffer.
+#[repr(C, align(64))]
+struct PropertyBuffer([u32; 16]);
+
+//----------------------------------------
+// Public Definitions
+//----------------------------------------
+
+pub struct MailboxInner {
+    registers: Registers,
+    buffer: PropertyBuffer,
+}
+
+pub struct Mailbox {
+    inner: NullLock<MailboxInner>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl MailboxInner {
+    /// Create MailboxInner instance
+    ///
+    /// # Safety
+    ///
+    /// - verify mmio start address
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            registers: Registers::new(mmio_start_addr),
+            buffer: PropertyBuffer([0; 16]),
+        }
+    }
+
+    /// Send the message in the buffer over the property channel and wait for the answer
+    fn call(&mut self) -> Result<(), &'static str> {
+        // the firmware reads (and answers into) the buffer behind the compiler's back
+        let addr = self.buffer.0.as_mut_ptr() as usize as u32;
+        let message = (addr & !0xF) | CHANNEL_PROPERTY;
+
+        // the firmware doesn't look at the ARM's data cache
+        let buffer_len = core::mem::size_of::<PropertyBuffer>();
+        memory::clean_invalidate_dcache_range(addr as usize, buffer_len);
+
+        let deadline = Deadline::after(TIMEOUT);
+
+        while self.registers.WRITE_STATUS.is_set(STATUS::FULL) {
+            if deadline.has_passed() {
+                return Err("mailbox timed out");
+            }
+            cpu::nop();
+        }
+        self.registers.WRITE.set(message);
+
+        loop {
+            while self.registers.STATUS.is_set(STATUS::EMPTY) {
+                if deadline.has_passed() {
+                    return Err("mailbox timed out");
+                }
+                cpu::nop();
+            }
+
+            // skip answers to other channels
+            if self.registers.READ.get() == message {
+                break;
+            }
+        }
+
+        memory::invalidate_dcache_range(addr as usize, buffer_len);
+        let code = unsafe { core::ptr::read_volatile(&self.buffer.0[1]) };
+        if code == RESPONSE_SUCCESS {
+            Ok(())
+        } else {
+            Err("mailbox property call failed")
+        }
+    }
+
+    /// Set the state of a GPIO owned by the firmware (e.g. 130, the RPi3's activity LED)
+    fn set_gpio_state(&mut self, pin: u32, on: bool) -> Result<(), &'static str> {
+        let message = [
+            8 * 4, // buffer size in bytes
+            REQUEST,
+            TAG_SET_GPIO_STATE,
+            8, // value buffer size
+            0, // tag request
+            pin,
+            on as u32,
+            TAG_END,
+        ];
+
+        for (i, word) in message.iter().enumerate() {
+            unsafe { core::ptr::write_volatile(&mut self.buffer.0[i], *word) };
+        }
+
+        self.call()
+    }
+}
+
+impl Mailbox {
+    /// Create new instance
+    ///
+    /// # Safety
+    ///
+    /// - Provide correct MMIO start address
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            inner: NullLock::new(MailboxInner::new(mmio_start_addr)),
+        }
+    }
+
+    /// Set the state of a GPIO owned by the firmware
+    pub fn set_gpio_state(&self, pin: u32, on: bool) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.set_gpio_state(pin, on))
+    }
+}
+
+// -----------------------------------------------
+// Interface code
+// -----------------------------------------------
+
+impl driver::interface::DeviceDriver for Mailbox {
+    fn compatible(&self) -> &'static str {
+        "BCM VideoCore Mailbox"
+    }
+}
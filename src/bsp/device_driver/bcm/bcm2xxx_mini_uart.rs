@@ -0,0 +1,346 @@
+/*
+ * File: bcm2xxx_mini_uart.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 10:12:31 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Mini UART (AUX UART1) driver.
+//!
+//! On boards where the PL011 is routed to the bluetooth chip, the mini UART is the only UART on
+//! GPIO 14/15. It is clocked from the VPU core clock, so its baudrate depends on `core_freq`.
+
+use core::fmt;
+use core::fmt::Arguments;
+use core::time::Duration;
+
+use crate::{
+    bsp::device_driver::common::MMIODerefWrapper, console, cpu, driver,
+    synchronization::interface::Mutex, synchronization::NullLock, time::Deadline,
+};
+
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_bitfields, register_structs,
+    registers::ReadOnly, registers::ReadWrite,
+};
+
+//----------------------------------------
+// private stuff
+//----------------------------------------
+
+// Auxiliary peripherals registers (mini UART part only).
+//
+// Descriptions taken from
+// - https://github.com/raspberrypi/documentation/files/1888662/BCM2837-ARM-Peripherals.-.Revised.-.V2-1.pdf
+//   (chapter 2, including the errata for AUX_MU_LCR_REG and AUX_MU_IIR_REG)
+
+register_bitfields! {
+    u32, // 32 bit wide
+
+    /// Auxiliary enables
+    AUX_ENABLES [
+        /// If set the mini UART is enabled. The UART will immediately start receiving data,
+        /// especially if the UART1_RX line is low.
+        /// If clear the mini UART is disabled. That also disables any mini UART register access
+        MINI_UART OFFSET(0) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ]
+    ],
+
+    /// Mini UART interrupt enable
+    AUX_MU_IER_REG [
+        /// Enable receive interrupts
+        RX_INT OFFSET(0) NUMBITS(1) [],
+
+        /// Enable transmit interrupts
+        TX_INT OFFSET(1) NUMBITS(1) []
+    ],
+
+    /// Mini UART interrupt identify. On write, used to clear the FIFOs
+    AUX_MU_IIR_REG [
+        /// On write: clear the receive and/or transmit FIFO
+        FIFO_CLEAR OFFSET(1) NUMBITS(2) [
+            Rx = 0b01,
+            Tx = 0b10,
+            All = 0b11
+        ]
+    ],
+
+    /// Mini UART line control
+    AUX_MU_LCR_REG [
+        /// Data size. The datasheet only documents bit 0, but (errata) both bits have to be set
+        /// to get 8 bit mode.
+        DATA_SIZE OFFSET(0) NUMBITS(2) [
+            SevenBit = 0b00,
+            EightBit = 0b11
+        ]
+    ],
+
+    /// Mini UART line status
+    AUX_MU_LSR_REG [
+        /// This bit is set if the receive FIFO holds at least 1 symbol.
+        DATA_READY OFFSET(0) NUMBITS(1) [],
+
+        /// This bit is set if there was a receiver overrun.
+        RX_OVERRUN OFFSET(1) NUMBITS(1) [],
+
+        /// This bit is set if the transmit FIFO can accept at least one byte.
+        TX_EMPTY OFFSET(5) NUMBITS(1) [],
+
+        /// This bit is set if the transmit FIFO is empty and the transmitter is idle.
+        TX_IDLE OFFSET(6) NUMBITS(1) []
+    ],
+
+    /// Mini UART extra control
+    AUX_MU_CNTL_REG [
+        /// Receiver enable
+        RX_ENABLE OFFSET(0) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ],
+
+        /// Transmitter enable
+        TX_ENABLE OFFSET(1) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ]
+    ],
+
+    /// Mini UART baudrate
+    AUX_MU_BAUD_REG [
+        /// baudrate = system_clock_freq / (8 * (baudrate_reg + 1))
+        BAUDRATE OFFSET(0) NUMBITS(16) []
+    ]
+}
+
+register_structs! {
+    #[allow(non_snake_case)]
+    pub RegisterBlock {
+        (0x00 => _reserved1), // AUX_IRQ
+        (0x04 => AUX_ENABLES: ReadWrite<u32, AUX_ENABLES::Register>),
+        (0x08 => _reserved2),
+        (0x40 => AUX_MU_IO_REG: ReadWrite<u32>),
+        (0x44 => AUX_MU_IER_REG: ReadWrite<u32, AUX_MU_IER_REG::Register>),
+        (0x48 => AUX_MU_IIR_REG: ReadWrite<u32, AUX_MU_IIR_REG::Register>),
+        (0x4c => AUX_MU_LCR_REG: ReadWrite<u32, AUX_MU_LCR_REG::Register>),
+        (0x50 => AUX_MU_MCR_REG: ReadWrite<u32>),
+        (0x54 => AUX_MU_LSR_REG: ReadOnly<u32, AUX_MU_LSR_REG::Register>),
+        (0x58 => _reserved3), // MSR, SCRATCH
+        (0x60 => AUX_MU_CNTL_REG: ReadWrite<u32, AUX_MU_CNTL_REG::Register>),
+        (0x64 => _reserved4), // STAT
+        (0x68 => AUX_MU_BAUD_REG: ReadWrite<u32, AUX_MU_BAUD_REG::Register>),
+        (0x6c => @END),
+    }
+}
+
+// abtracts the register calling
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// Baudrate used by the loader, same as the PL011
+const BAUDRATE: u32 = 115_200;
+
+enum BlockingMode {
+    Blocking,
+    NonBlocking,
+    /// Block until the deadline passes
+    Timeout(Deadline),
+}
+
+//----------------------------------------
+// Public Definitions
+//----------------------------------------
+
+pub struct MiniUartInner {
+    registers: Registers,
+    core_clock_hz: u32,
+    chars_written: usize,
+    chars_read: usize,
+}
+
+// Export the inner uart struct so panic handlers could use it even if the main uart driver crashed
+pub use MiniUartInner as PanicMiniUart;
+
+pub struct MiniUart {
+    inner: NullLock<MiniUartInner>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl MiniUartInner {
+    /// Create MiniUartInner instance
+    ///
+    /// # Safety
+    ///
+    /// - verify mmio start address (start of the AUX block, not of the mini UART registers)
+    pub const unsafe fn new(mmio_start_addr: usize, core_clock_hz: u32) -> Self {
+        Self {
+            registers: Registers::new(mmio_start_addr),
+            core_clock_hz,
+            chars_written: 0,
+            chars_read: 0,
+        }
+    }
+
+    /// Enable the mini UART in the AUX block and set it up as 8N1, 115200 baudrate.
+    ///
+    /// Calculation (core clock is 250MHz on the RPi3 when `enable_uart=1`):
+    /// AUX_MU_BAUD = core_clock_in_hertz/(8*desired_baudrate) - 1
+    ///
+    /// AUX_MU_BAUD = 250,000,000/(8*115200) - 1 = 270.27 (270)
+    ///
+    /// baudrate is 250,000,000/(8*271) = ~115313
+    /// error: (115313-115200)/115200 * 100 = 0.1%
+    pub fn init(&mut self) {
+        // The AUX block gates register access, so flushing is only possible when it is already
+        // enabled (i.e. when the panic handler re-initializes the UART).
+        if self.registers.AUX_ENABLES.matches_all(AUX_ENABLES::MINI_UART::Enabled) {
+            self.flush();
+        }
+
+        // enable the mini UART, otherwise its registers can't be accessed
+        self.registers.AUX_ENABLES.write(AUX_ENABLES::MINI_UART::Enabled);
+
+        // disable TX, RX and interrupts while configuring
+        self.registers.AUX_MU_CNTL_REG.set(0);
+        self.registers.AUX_MU_IER_REG.set(0);
+
+        // 8 bit mode, RTS line high
+        self.registers.AUX_MU_LCR_REG.write(AUX_MU_LCR_REG::DATA_SIZE::EightBit);
+        self.registers.AUX_MU_MCR_REG.set(0);
+
+        // clear both FIFOs
+        self.registers.AUX_MU_IIR_REG.write(AUX_MU_IIR_REG::FIFO_CLEAR::All);
+
+        let divisor = self.core_clock_hz / (8 * BAUDRATE) - 1;
+        self.registers
+            .AUX_MU_BAUD_REG
+            .write(AUX_MU_BAUD_REG::BAUDRATE.val(divisor));
+
+        // turn TX and RX on
+        self.registers
+            .AUX_MU_CNTL_REG
+            .write(AUX_MU_CNTL_REG::RX_ENABLE::Enabled + AUX_MU_CNTL_REG::TX_ENABLE::Enabled);
+    }
+
+    /// Write Char
+    fn write_char(&mut self, c: char) {
+        // wait for an empty fifo slot!
+        while !self.registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::TX_EMPTY) {
+            cpu::nop();
+        }
+
+        self.registers.AUX_MU_IO_REG.set(c as u32);
+
+        self.chars_written += 1;
+    }
+
+    /// Blocks execution until the transmit FIFO is empty and the transmitter is idle
+    fn flush(&self) {
+        while !self.registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::TX_IDLE) {
+            cpu::nop();
+        }
+    }
+
+    /// Read a character Blocking / Non-Blocking / with a deadline
+    fn read_char(&mut self, blocking_mode: BlockingMode) -> Option<char> {
+        while !self.registers.AUX_MU_LSR_REG.is_set(AUX_MU_LSR_REG::DATA_READY) {
+            match blocking_mode {
+                // return if non blocking mode or out of time
+                BlockingMode::NonBlocking => return None,
+                BlockingMode::Timeout(deadline) if deadline.has_passed() => return None,
+                // otherwise wait for a character
+                _ => cpu::nop(),
+            }
+        }
+
+        let ret = self.registers.AUX_MU_IO_REG.get() as u8 as char;
+
+        self.chars_read += 1;
+        Some(ret)
+    }
+}
+
+/// See the matching implementation for the PL011 in [`super::PL011UartInner`].
+impl fmt::Write for MiniUartInner {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.write_char(c);
+        }
+        Ok(())
+    }
+}
+
+impl MiniUart {
+    /// Create new instance
+    ///
+    /// # Safety
+    ///
+    /// - Provide correct MMIO start address of the AUX block
+    pub const unsafe fn new(mmio_start_addr: usize, core_clock_hz: u32) -> Self {
+        Self {
+            inner: NullLock::new(MiniUartInner::new(mmio_start_addr, core_clock_hz)),
+        }
+    }
+}
+
+// -----------------------------------------------
+// Interface code
+// -----------------------------------------------
+
+impl driver::interface::DeviceDriver for MiniUart {
+    fn compatible(&self) -> &'static str {
+        "BCM Mini UART"
+    }
+
+    fn init(&self) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.init());
+        Ok(())
+    }
+}
+
+impl console::interface::Write for MiniUart {
+    fn write_char(&self, c: char) {
+        self.inner.lock(|inner| inner.write_char(c));
+    }
+
+    fn write_fmt(&self, args: Arguments) -> fmt::Result {
+        self.inner.lock(|inner| fmt::Write::write_fmt(inner, args))
+    }
+
+    fn flush(&self) {
+        self.inner.lock(|inner| inner.flush());
+    }
+}
+
+impl console::interface::Read for MiniUart {
+    fn read_char(&self) -> char {
+        self.inner
+            .lock(|inner| inner.read_char(BlockingMode::Blocking)
+            .unwrap())
+    }
+    fn try_read_char(&self) -> Option<char> {
+        self.inner.lock(|inner| inner.read_char(BlockingMode::NonBlocking))
+    }
+    fn read_char_timeout(&self, timeout: Duration) -> Option<char> {
+        let deadline = Deadline::after(timeout);
+        self.inner.lock(|inner| inner.read_char(BlockingMode::Timeout(deadline)))
+    }
+    fn clear_rx(&self) {
+        while self.try_read_char().is_some() {}
+    }
+}
+
+impl console::interface::Statistics for MiniUart {
+    fn chars_written(&self) -> usize {
+        self.inner.lock(|inner|inner.chars_written)
+    }
+    fn chars_read(&self) -> usize {
+        self.inner.lock(|inner|inner.chars_read)
+    }
+}
+
+impl console::interface::All for MiniUart {}
\ No newline at end of file
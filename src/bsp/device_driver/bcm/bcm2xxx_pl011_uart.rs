@@ -7,10 +7,15 @@
 
 use core::fmt;
 use core::fmt::Arguments;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use core::time::Duration;
 
 use crate::{
-    bsp::device_driver::common::MMIODerefWrapper, driver, synchronization::interface::Mutex,
-    synchronization::NullLock, cpu, console,
+    bsp::device_driver::common::MMIODerefWrapper, bsp::device_driver::Dma,
+    bsp::device_driver::DmaChannel, bsp::device_driver::Dreq,
+    bsp::exception::asynchronous::IRQNumber, driver,
+    exception, memory, synchronization::interface::Mutex, synchronization::NullLock,
+    synchronization::RingBuffer, cpu, console, time::Deadline,
 };
 
 use tock_registers::{
@@ -32,6 +37,16 @@ use tock_registers::{
 register_bitfields! {
     u32, // 32 bit wide
 
+    /// Data Register
+    DR [
+        /// Overrun error. Set if data was received while the receive FIFO was already full. The
+        /// received data is lost, this character is the one read after it.
+        OE OFFSET(11) NUMBITS(1) [],
+
+        /// Received data character
+        DATA OFFSET(0) NUMBITS(8) []
+    ],
+
     /// Flag Register
     FR [
         /// UART busy. If this bit is set to 1, the UART is busy transmitting data. This bit remains
@@ -128,17 +143,77 @@ register_bitfields! {
         ]
     ],
 
+    /// Interrupt FIFO Level Select Register
+    IFLS [
+        /// Receive interrupt FIFO level select. The RX interrupt is asserted once the receive
+        /// FIFO fills beyond this level.
+        RXIFLSEL OFFSET(3) NUMBITS(3) [
+            OneEighth = 0b000,
+            OneQuarter = 0b001,
+            OneHalf = 0b010,
+            ThreeQuarters = 0b011,
+            SevenEighths = 0b100
+        ]
+    ],
+
+    /// Interrupt Mask Set/Clear Register. A set bit enables the interrupt.
+    IMSC [
+        /// Overrun error interrupt mask
+        OEIM OFFSET(10) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ],
+
+        /// Receive timeout interrupt mask. Asserted when the receive FIFO is not empty and no
+        /// more data arrived for 32 bit periods, so the tail of a transfer isn't stuck below the
+        /// RX level.
+        RTIM OFFSET(6) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ],
+
+        /// Receive interrupt mask
+        RXIM OFFSET(4) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ]
+    ],
+
+    /// Masked Interrupt Status Register
+    MIS [
+        /// Overrun error masked interrupt status
+        OEMIS OFFSET(10) NUMBITS(1) [],
+
+        /// Receive timeout masked interrupt status
+        RTMIS OFFSET(6) NUMBITS(1) [],
+
+        /// Receive masked interrupt status
+        RXMIS OFFSET(4) NUMBITS(1) []
+    ],
+
     /// Interrupt Clear Register.
     ICR [
+        /// Overrun error interrupt clear
+        OEIC OFFSET(10) NUMBITS(1) [],
+
         /// Meta field for all pending interrupts.
         ALL OFFSET(0) NUMBITS(11) []
+    ],
+
+    /// DMA Control Register
+    DMACR [
+        /// Receive DMA enable: raise the RX DREQ while the receive FIFO holds data
+        RXDMAE OFFSET(0) NUMBITS(1) [
+            Disabled = 0,
+            Enabled = 1
+        ]
     ]
 }
 
 register_structs! {
     #[allow(non_snake_case)]
     pub RegisterBlock {
-        (0x00 => DR: ReadWrite<u32>),
+        (0x00 => DR: ReadWrite<u32, DR::Register>),
         (0x04 => _reserved1),
         (0x18 => FR: ReadOnly<u32, FR::Register>),
         (0x1c => _reserved2), // CHECK
@@ -146,19 +221,35 @@ register_structs! {
         (0x28 => FBRD: WriteOnly<u32, FBRD::Register>),
         (0x2c => LCR_H: ReadWrite<u32, LCR_H::Register>),
         (0x30 => CR: WriteOnly<u32, CR::Register>),
-        (0x34 => _reserved3),
+        (0x34 => IFLS: ReadWrite<u32, IFLS::Register>),
+        (0x38 => IMSC: ReadWrite<u32, IMSC::Register>),
+        (0x3c => _reserved3), // RIS, the unmasked status, isn't needed
+        (0x40 => MIS: ReadOnly<u32, MIS::Register>),
         (0x44 => ICR: WriteOnly<u32, ICR::Register>),
-        (0x48 => @END),
+        (0x48 => DMACR: ReadWrite<u32, DMACR::Register>),
+        (0x4c => @END),
     }
 }
 
 // abtracts the register calling
 type Registers = MMIODerefWrapper<RegisterBlock>;
 
-#[derive(PartialEq)]
+/// Received bytes waiting to be read. At 921600 baud this is ~40ms of data.
+const RX_BUFFER_SIZE: usize = 4096;
+
+/// Words the DMA engine fills with received DR values (a byte and its error flags each), ~180ms
+/// at 921600 baud. Drained into `rx_buffer` by readers.
+const RX_DMA_RING_WORDS: usize = 4096;
+
+/// Cache line aligned, so invalidating it can't throw away the core's writes to its neighbours
+#[repr(C, align(64))]
+struct RxDmaRing([AtomicU32; RX_DMA_RING_WORDS]);
+
 enum BlockingMode {
     Blocking,
     NonBlocking,
+    /// Block until the deadline passes
+    Timeout(Deadline),
 }
 //----------------------------------------
 // Public Definitions
@@ -170,11 +261,59 @@ pub struct PL011UartInner {
     chars_read: usize,
 }
 
+/// How a PL011 receives by DMA: the controller, the UART's DREQ, and its data register as the
+/// DMA engine sees it
+pub struct PL011RxDma {
+    pub controller: &'static Dma,
+    pub dreq: Dreq,
+    pub data_register_bus_addr: u32,
+}
+
 // Export the inner uart struct so panic handlers could use it even if the main uart driver crashed
+#[cfg(not(feature = "console_mini_uart"))]
 pub use PL011UartInner as PanicUart;
 
+/// The PL011 with interrupt driven receive: the IRQ handler drains the receive FIFO into
+/// `rx_buffer`, readers consume from there.
+///
+/// Given a [`PL011RxDma`], the DMA engine empties the receive FIFO into `rx_dma_ring` instead,
+/// and readers move it on to `rx_buffer`. The IRQ handler then only counts receive FIFO
+/// overruns. Without a free channel it falls back to the IRQ path.
 pub struct PL011Uart {
     inner: NullLock<PL011UartInner>,
+
+    /// Receive path registers, used without taking the lock so the IRQ handler can't deadlock
+    /// against (or alias) a writer holding it
+    rx_registers: Registers,
+
+    /// Filled by the IRQ handler (or by readers while it can't run), emptied by readers
+    rx_buffer: RingBuffer<RX_BUFFER_SIZE>,
+
+    /// Received bytes lost, to a receive FIFO overrun or a full `rx_buffer`
+    rx_overruns: AtomicUsize,
+
+    /// Receive FIFO overruns while the DMA engine receives. Counted by the IRQ handler, apart
+    /// from `rx_overruns` which has the readers as its producer then.
+    rx_fifo_overruns: AtomicUsize,
+
+    /// Set once the RX interrupts are enabled and the IRQ handler registered
+    rx_irq_enabled: AtomicBool,
+
+    irq_number: IRQNumber,
+
+    rx_dma: Option<PL011RxDma>,
+
+    /// The channel filling `rx_dma_ring`, if receiving by DMA
+    rx_dma_channel: NullLock<Option<DmaChannel>>,
+
+    /// Written by the DMA engine, read by readers
+    rx_dma_ring: RxDmaRing,
+
+    /// Words of `rx_dma_ring` read (or lost) since the DMA engine started filling it
+    rx_dma_read: AtomicUsize,
+
+    /// Set while the DMA engine receives
+    rx_dma_enabled: AtomicBool,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -224,9 +363,13 @@ impl PL011UartInner {
         // disable uart
         self.registers.CR.set(0);
 
-        // clear interupts
+        // mask and clear interupts, PL011Uart turns RX interrupts on once it has a handler
+        self.registers.IMSC.set(0);
         self.registers.ICR.write(ICR::ALL::CLEAR);
 
+        // no DMA either, PL011Uart turns it on if it has a channel
+        self.registers.DMACR.set(0);
+
         // set IBRD + FBRD and enable FIFO and 8N1
         self.registers.IBRD.write(IBRD::IBRD_DIVINT.val(26));
         self.registers.FBRD.write(FBRD::FBRD_DIVFRAC.val(3));
@@ -259,26 +402,29 @@ impl PL011UartInner {
         }
     }
 
-    /// Read a character Blocking / Non-Blocking mode
-    fn read_char(&mut self, blocking_mode: BlockingMode) -> Option<char> {
-        // What if RXF is empty
-        if self.registers.FR.matches_all(FR::RXFE::SET) {
-            // return if non blocking mode
-            if blocking_mode == BlockingMode::NonBlocking {
-                return None;
-            }
+    /// Interrupt once the receive FIFO is half full (8 of 16 bytes), or holds anything and the
+    /// line went quiet
+    fn enable_rx_irq(&mut self) {
+        self.registers.IFLS.write(IFLS::RXIFLSEL::OneHalf);
+        self.registers.IMSC.write(IMSC::RXIM::Enabled + IMSC::RTIM::Enabled);
+    }
 
-            // otherwise wait for a character
-            while self.registers.FR.matches_all(FR::RXFE::SET) {
-                cpu::nop();
-            }
-        }
+    fn enable_rx_dma(&mut self) {
+        self.registers.DMACR.write(DMACR::RXDMAE::Enabled);
+    }
 
-        // read char
-        let ret = self.registers.DR.get() as u8 as char;
+    /// Interrupt on a receive FIFO overrun only, the DMA engine takes care of the rest
+    fn enable_overrun_irq(&mut self) {
+        self.registers.IMSC.write(IMSC::OEIM::Enabled);
+    }
+
+    /// Undo `enable_rx_irq`, `enable_overrun_irq` and `enable_rx_dma`. The UART stays on, for the image's early console.
+    fn quiesce(&mut self) {
+        self.flush();
 
-        self.chars_read += 1;
-        Some(ret)
+        self.registers.IMSC.set(0);
+        self.registers.ICR.write(ICR::ALL::CLEAR);
+        self.registers.DMACR.set(0);
     }
 }
 
@@ -306,9 +452,150 @@ impl PL011Uart {
     /// # Safety
     ///
     /// - Provide correct MMIO start address
-    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+    pub const unsafe fn new(
+        mmio_start_addr: usize,
+        irq_number: IRQNumber,
+        rx_dma: Option<PL011RxDma>,
+    ) -> Self {
+        const EMPTY_WORD: AtomicU32 = AtomicU32::new(0);
+
         Self {
             inner: NullLock::new(PL011UartInner::new(mmio_start_addr)),
+            rx_registers: Registers::new(mmio_start_addr),
+            rx_buffer: RingBuffer::new(),
+            rx_overruns: AtomicUsize::new(0),
+            rx_fifo_overruns: AtomicUsize::new(0),
+            rx_irq_enabled: AtomicBool::new(false),
+            irq_number,
+            rx_dma,
+            rx_dma_channel: NullLock::new(None),
+            rx_dma_ring: RxDmaRing([EMPTY_WORD; RX_DMA_RING_WORDS]),
+            rx_dma_read: AtomicUsize::new(0),
+            rx_dma_enabled: AtomicBool::new(false),
+        }
+    }
+
+    /// Claim a DMA channel and keep it filling `rx_dma_ring` from the receive FIFO
+    fn start_rx_dma(&self, rx_dma: &PL011RxDma) -> Result<(), &'static str> {
+        let channel = rx_dma.controller.claim_channel("PL011 UART receive")?;
+
+        // the ring is part of a static, it outlives the channel
+        unsafe {
+            rx_dma.controller.start_ring_from_peripheral(
+                &channel,
+                rx_dma.dreq,
+                rx_dma.data_register_bus_addr,
+                &self.rx_dma_ring.0,
+            )
+        };
+        self.inner.lock(|inner| inner.enable_rx_dma());
+
+        self.rx_dma_channel.lock(|slot| *slot = Some(channel));
+        self.rx_dma_enabled.store(true, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Move what the DMA engine received so far to `rx_buffer`. Called by readers only.
+    ///
+    /// The DMA engine doesn't wait for readers: once it's a whole ring ahead it overwrites words
+    /// nobody read, and those count as overruns.
+    fn drain_rx_dma(&self, rx_dma: &PL011RxDma) {
+        let written = match self.rx_dma_channel.lock(|slot| {
+            slot.as_ref().map(|channel| rx_dma.controller.ring_position(channel) / 4)
+        }) {
+            Some(written) => written,
+            None => return,
+        };
+        let mut read = self.rx_dma_read.load(Ordering::Relaxed);
+
+        // the pass that just ended isn't counted yet, readers are never ahead of the DMA engine
+        let written = if written < read { written + RX_DMA_RING_WORDS } else { written };
+
+        if written - read > RX_DMA_RING_WORDS {
+            let lost = written - read - RX_DMA_RING_WORDS;
+            let overruns = self.rx_overruns.load(Ordering::Relaxed);
+            self.rx_overruns.store(overruns + lost, Ordering::Relaxed);
+
+            read = written - RX_DMA_RING_WORDS;
+        }
+
+        // the DMA engine wrote RAM, not the cache
+        let ring = &self.rx_dma_ring.0;
+        let ring_addr = |index: usize| ring[index].as_ptr() as usize;
+        let start = read % RX_DMA_RING_WORDS;
+        let len = written - read;
+        if start + len <= RX_DMA_RING_WORDS {
+            memory::invalidate_dcache_range(ring_addr(start), len * 4);
+        } else {
+            memory::invalidate_dcache_range(ring_addr(start), (RX_DMA_RING_WORDS - start) * 4);
+            memory::invalidate_dcache_range(ring_addr(0), (start + len - RX_DMA_RING_WORDS) * 4);
+        }
+
+        while read != written {
+            let word = ring[read % RX_DMA_RING_WORDS].load(Ordering::Acquire);
+            let data = tock_registers::LocalRegisterCopy::<u32, DR::Register>::new(word);
+
+            // DR::OE is left to the overrun interrupt
+            if !unsafe { self.rx_buffer.push(data.read(DR::DATA) as u8) } {
+                self.count_overrun();
+            }
+
+            read += 1;
+        }
+        self.rx_dma_read.store(read, Ordering::Relaxed);
+    }
+
+    /// Move everything in the receive FIFO to `rx_buffer`.
+    ///
+    /// Only ever called by one producer at a time: the IRQ handler, or a reader while the IRQ
+    /// handler can't run.
+    fn drain_rx_fifo(&self) {
+        while !self.rx_registers.FR.matches_all(FR::RXFE::SET) {
+            let data = self.rx_registers.DR.extract();
+
+            if data.is_set(DR::OE) {
+                self.count_overrun();
+            }
+            if !unsafe { self.rx_buffer.push(data.read(DR::DATA) as u8) } {
+                self.count_overrun();
+            }
+        }
+    }
+
+    /// Only the (single) producer counts, so a load and a store do
+    fn count_overrun(&self) {
+        let overruns = self.rx_overruns.load(Ordering::Relaxed);
+        self.rx_overruns.store(overruns + 1, Ordering::Relaxed);
+    }
+
+    /// Read a character Blocking / Non-Blocking / with a deadline
+    fn receive_char(&self, blocking_mode: BlockingMode) -> Option<char> {
+        loop {
+            if let (true, Some(rx_dma)) =
+                (self.rx_dma_enabled.load(Ordering::Acquire), &self.rx_dma)
+            {
+                self.drain_rx_dma(rx_dma);
+            }
+            // nothing fills the buffer behind our back if the IRQ handler can't run
+            else if !self.rx_irq_enabled.load(Ordering::Acquire)
+                || exception::asynchronous::is_local_irq_masked()
+            {
+                self.drain_rx_fifo();
+            }
+
+            if let Some(byte) = unsafe { self.rx_buffer.pop() } {
+                self.inner.lock(|inner| inner.chars_read += 1);
+                return Some(byte as char);
+            }
+
+            match blocking_mode {
+                // return if non blocking mode or out of time
+                BlockingMode::NonBlocking => return None,
+                BlockingMode::Timeout(deadline) if deadline.has_passed() => return None,
+                // otherwise wait for a character
+                _ => cpu::nop(),
+            }
         }
     }
 }
@@ -321,9 +608,86 @@ impl driver::interface::DeviceDriver for PL011Uart {
     fn compatible(&self) -> &'static str {
         "BCM PL011 UART"
     }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        match self.rx_dma {
+            Some(_) => &[Dma::COMPATIBLE],
+            None => &[],
+        }
+    }
     
     fn init(&self) -> Result<(), &'static str> {
         self.inner.lock(|inner| inner.init());
+
+        if let Some(rx_dma) = &self.rx_dma {
+            // No free channel is fine, the IRQ path receives instead.
+            let _ = self.start_rx_dma(rx_dma);
+        }
+
+        Ok(())
+    }
+
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        use exception::asynchronous::{interface::IRQManager, irq_manager, IRQDescriptor};
+
+        let descriptor = IRQDescriptor {
+            name: "BCM PL011 UART",
+            handler: self,
+        };
+
+        irq_manager().register_handler(self.irq_number, descriptor)?;
+        irq_manager().enable(self.irq_number);
+
+        // the DMA engine empties the receive FIFO, only overruns are left to interrupt for
+        if self.rx_dma_enabled.load(Ordering::Acquire) {
+            self.inner.lock(|inner| inner.enable_overrun_irq());
+            return Ok(());
+        }
+
+        // exec_with_irq_masked: the handler mustn't run before the readers stop draining
+        exception::asynchronous::exec_with_irq_masked(|| {
+            self.inner.lock(|inner| inner.enable_rx_irq());
+            self.rx_irq_enabled.store(true, Ordering::Release);
+        });
+
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            self.inner.lock(|inner| inner.quiesce());
+            self.rx_irq_enabled.store(false, Ordering::Release);
+        });
+
+        // the UART no longer requests data, the channel can go
+        if let Some(rx_dma) = &self.rx_dma {
+            if let Some(channel) = self.rx_dma_channel.lock(|slot| slot.take()) {
+                self.rx_dma_enabled.store(false, Ordering::Release);
+                rx_dma.controller.release_channel(channel);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQHandler for PL011Uart {
+    fn handle(&self) -> Result<(), &'static str> {
+        // Another PL011 sharing the IRQ line may have raised it, this one has nothing to do then.
+        // Reading the FIFO empty clears both RX interrupts.
+        let pending = self.rx_registers.MIS.extract();
+        if pending.is_set(MIS::RXMIS) || pending.is_set(MIS::RTMIS) {
+            self.drain_rx_fifo();
+        }
+
+        // only enabled while the DMA engine receives
+        if pending.is_set(MIS::OEMIS) {
+            self.rx_registers.ICR.write(ICR::OEIC::SET);
+
+            let overruns = self.rx_fifo_overruns.load(Ordering::Relaxed);
+            self.rx_fifo_overruns.store(overruns + 1, Ordering::Relaxed);
+        }
+
         Ok(())
     }
 }
@@ -344,14 +708,17 @@ impl console::interface::Write for PL011Uart {
 
 impl console::interface::Read for PL011Uart {
     fn read_char(&self) -> char {
-        self.inner
-            .lock(|inner| inner.read_char(BlockingMode::Blocking)
-            .unwrap())
+        self.receive_char(BlockingMode::Blocking).unwrap()
+    }
+    fn try_read_char(&self) -> Option<char> {
+        self.receive_char(BlockingMode::NonBlocking)
+    }
+    fn read_char_timeout(&self, timeout: Duration) -> Option<char> {
+        let deadline = Deadline::after(timeout);
+        self.receive_char(BlockingMode::Timeout(deadline))
     }
     fn clear_rx(&self) {
-        while self.inner
-            .lock(|inner| inner.read_char(BlockingMode::NonBlocking)
-            .is_some()) {}
+        while self.try_read_char().is_some() {}
     }
 }
 
@@ -362,4 +729,9 @@ impl console::interface::Statistics for PL011Uart {
     fn chars_read(&self) -> usize {
         self.inner.lock(|inner|inner.chars_read)
     }
+    fn rx_overruns(&self) -> usize {
+        self.rx_overruns.load(Ordering::Relaxed) + self.rx_fifo_overruns.load(Ordering::Relaxed)
+    }
 }
+
+impl console::interface::All for PL011Uart {}
\ No newline at end of file
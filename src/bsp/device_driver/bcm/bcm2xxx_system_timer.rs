@@ -0,0 +1,239 @@
+/*
+ * File: bcm2xxx_system_timer.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 9:02:31 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BCM system timer driver.
+//!
+//! A free-running 64 bit counter ticking at 1 MHz, clocked independently of the ARM cores, so
+//! frequency scaling doesn't affect it. Four 32 bit compare channels match against its low word;
+//! a match sets the channel's bit in CS and raises the channel's interrupt line.
+//!
+//! Channels 0 and 2 are used by the VideoCore firmware, only 1 and 3 are free for the ARM.
+//!
+//! Descriptions taken from
+//! - https://github.com/raspberrypi/documentation/files/1888662/BCM2837-ARM-Peripherals.-.Revised.-.V2-1.pdf
+//!   (chapter 12, System Timer)
+
+use crate::{
+    bsp::{device_driver::common::MMIODerefWrapper, exception::asynchronous::IRQNumber},
+    driver, exception, synchronization::interface::Mutex, synchronization::NullLock,
+};
+use core::time::Duration;
+
+use tock_registers::{
+    interfaces::{Readable, Writeable},
+    register_structs,
+    registers::{ReadOnly, ReadWrite},
+};
+
+//----------------------------------------
+// private stuff
+//----------------------------------------
+
+register_structs! {
+    #[allow(non_snake_case)]
+    pub RegisterBlock {
+        // control/status: bit n is set on a channel n match, write 1 to clear it
+        (0x00 => CS: ReadWrite<u32>),
+        // counter, lower and upper 32 bits
+        (0x04 => CLO: ReadOnly<u32>),
+        (0x08 => CHI: ReadOnly<u32>),
+        // compare channels 0-3
+        (0x0c => C: [ReadWrite<u32>; 4]),
+        (0x1c => @END),
+    }
+}
+
+// abtracts the register calling
+type Registers = MMIODerefWrapper<RegisterBlock>;
+
+/// The channels the ARM may use
+const ARM_CHANNELS: [TimerChannel; 2] = [TimerChannel::C1, TimerChannel::C3];
+
+//----------------------------------------
+// Public Definitions
+//----------------------------------------
+
+/// Compare channels free for the ARM (0 and 2 belong to the VideoCore)
+#[derive(Copy, Clone)]
+pub enum TimerChannel {
+    C1 = 1,
+    C3 = 3,
+}
+
+pub struct SystemTimerInner {
+    registers: Registers,
+    /// Called from the IRQ handler on a channel's match, by channel number
+    on_match: [Option<fn()>; 4],
+}
+
+pub struct SystemTimer {
+    inner: NullLock<SystemTimerInner>,
+    /// The interrupt lines of channels C1 and C3
+    irq_numbers: [IRQNumber; 2],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl SystemTimerInner {
+    /// Create SystemTimerInner instance
+    ///
+    /// # Safety
+    ///
+    /// - verify mmio start address
+    pub const unsafe fn new(mmio_start_addr: usize) -> Self {
+        Self {
+            registers: Registers::new(mmio_start_addr),
+            on_match: [None; 4],
+        }
+    }
+
+    /// Disarm the ARM's compare channels: drop their callbacks and clear pending matches
+    pub fn init(&mut self) {
+        for channel in ARM_CHANNELS {
+            self.set_on_match(channel, None);
+            self.clear_match(channel);
+        }
+    }
+
+    /// Microseconds since the timer started counting
+    pub fn counter_us(&self) -> u64 {
+        // the two halves can't be read atomically: if the upper half changed while the lower
+        // one was read, the lower one wrapped, read it again
+        loop {
+            let hi = self.registers.CHI.get();
+            let lo = self.registers.CLO.get();
+
+            if self.registers.CHI.get() == hi {
+                return (u64::from(hi) << 32) | u64::from(lo);
+            }
+        }
+    }
+
+    /// Arm a channel to match `after` from now. The compare registers are 32 bits wide, longer
+    /// than ~71 minutes is refused.
+    pub fn arm(&mut self, channel: TimerChannel, after: Duration) -> Result<(), &'static str> {
+        let ticks = u32::try_from(after.as_micros()).map_err(|_| "Timer delay too long")?;
+
+        self.clear_match(channel);
+        self.registers.C[channel as usize].set(self.registers.CLO.get().wrapping_add(ticks));
+
+        Ok(())
+    }
+
+    /// Set what is called when a channel matches
+    pub fn set_on_match(&mut self, channel: TimerChannel, on_match: Option<fn()>) {
+        self.on_match[channel as usize] = on_match;
+    }
+
+    /// Clear a channel's match, which also drops its interrupt line
+    pub fn clear_match(&mut self, channel: TimerChannel) {
+        self.registers.CS.set(1 << channel as u32);
+    }
+
+    /// Clear the matches of the ARM's channels, returning what to call for each that matched
+    fn take_matches(&mut self) -> [Option<fn()>; 2] {
+        let matched = self.registers.CS.get();
+
+        ARM_CHANNELS.map(|channel| {
+            if matched & (1 << channel as u32) == 0 {
+                return None;
+            }
+
+            self.clear_match(channel);
+            self.on_match[channel as usize]
+        })
+    }
+}
+
+// Same as the inner methods, but with wrapping lock. The IRQ handler takes the lock too, so the
+// rest of the loader only takes it with IRQs masked.
+impl SystemTimer {
+    /// Create new instance
+    ///
+    /// # Safety
+    ///
+    /// - Provide correct MMIO start address
+    pub const unsafe fn new(mmio_start_addr: usize, irq_numbers: [IRQNumber; 2]) -> Self {
+        Self {
+            inner: NullLock::new(SystemTimerInner::new(mmio_start_addr)),
+            irq_numbers,
+        }
+    }
+
+    /// Microseconds since the timer started counting
+    pub fn counter_us(&self) -> u64 {
+        exception::asynchronous::exec_with_irq_masked(|| self.inner.lock(|inner| inner.counter_us()))
+    }
+
+    /// Arm a channel to match `after` from now, see [`SystemTimerInner::arm`]
+    pub fn arm(&self, channel: TimerChannel, after: Duration) -> Result<(), &'static str> {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            self.inner.lock(|inner| inner.arm(channel, after))
+        })
+    }
+
+    /// Call `on_match` from the IRQ handler when `channel` matches. The match is cleared first,
+    /// `on_match` may arm the channel again.
+    pub fn set_on_match(&self, channel: TimerChannel, on_match: fn()) {
+        exception::asynchronous::exec_with_irq_masked(|| {
+            self.inner.lock(|inner| inner.set_on_match(channel, Some(on_match)))
+        });
+    }
+}
+
+// -----------------------------------------------
+// Interface code
+// -----------------------------------------------
+
+impl driver::interface::DeviceDriver for SystemTimer {
+    fn compatible(&self) -> &'static str {
+        "BCM System Timer"
+    }
+
+    fn init(&self) -> Result<(), &'static str> {
+        self.inner.lock(|inner| inner.init());
+
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<(), &'static str> {
+        // the IRQ handler takes the lock too
+        exception::asynchronous::exec_with_irq_masked(|| self.inner.lock(|inner| inner.init()));
+
+        Ok(())
+    }
+
+    fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+        use exception::asynchronous::{interface::IRQManager, irq_manager, IRQDescriptor};
+
+        let descriptor = IRQDescriptor {
+            name: "BCM System Timer",
+            handler: self,
+        };
+
+        for irq_number in self.irq_numbers {
+            irq_manager().register_handler(irq_number, descriptor)?;
+            irq_manager().enable(irq_number);
+        }
+
+        Ok(())
+    }
+}
+
+impl exception::asynchronous::interface::IRQHandler for SystemTimer {
+    fn handle(&self) -> Result<(), &'static str> {
+        // called without the lock, they may well arm their channel again
+        let on_match = self.inner.lock(|inner| inner.take_matches());
+        for f in on_match.iter().flatten() {
+            f();
+        }
+
+        Ok(())
+    }
+}
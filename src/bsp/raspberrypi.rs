@@ -5,24 +5,192 @@
  * Author: Elad Matia (elad.matia@gmail.com)
  */
 
-//! board specific code for Raspberry Pi (currently 3) 
+//! board specific code for Raspberry Pi (3 and 4)
 
 pub mod cpu;
 pub mod console;
 pub mod driver;
+pub mod exception;
 pub mod memory;
+pub mod status;
 
 use super::device_driver;
+use crate::{
+    boot_mode::BootMode,
+    synchronization::{interface::ReadWriteEx, InitStateLock},
+    time,
+    time::interface::TimeManager,
+};
+use core::time::Duration;
 
 //-----------------------------------------------
 //      Global instances
 //-----------------------------------------------
 
+#[cfg(feature = "bsp_rpi3")]
+static INTERRUPT_CONTROLLER: device_driver::InterruptController =
+            unsafe {device_driver::InterruptController::new(
+                memory::map::mmio::LOCAL_IC_START,
+                memory::map::mmio::PERIPHERAL_IC_START,
+            ) };
+
+#[cfg(feature = "bsp_rpi4")]
+static INTERRUPT_CONTROLLER: device_driver::GICv2 =
+            unsafe {device_driver::GICv2::new(
+                memory::map::mmio::GICD_START,
+                memory::map::mmio::GICC_START,
+            ) };
+
 static GPIO: device_driver::GPIO =
             unsafe {device_driver::GPIO::new(memory::map::mmio::GPIO_START) };
 
-static PL011_UART: device_driver::PL011Uart =
-            unsafe {device_driver::PL011Uart::new(memory::map::mmio::PL011_UART_START) };
+static SYSTEM_TIMER: device_driver::SystemTimer =
+            unsafe {device_driver::SystemTimer::new(
+                memory::map::mmio::SYSTEM_TIMER_START,
+                [
+                    exception::asynchronous::irq_map::SYSTEM_TIMER_C1,
+                    exception::asynchronous::irq_map::SYSTEM_TIMER_C3,
+                ],
+            ) };
+
+/// The firmware's mailbox, the way to the RPi3's activity LED
+#[cfg(feature = "bsp_rpi3")]
+static MAILBOX: device_driver::Mailbox =
+            unsafe {device_driver::Mailbox::new(memory::map::mmio::MAILBOX_START) };
+
+static DMA: device_driver::Dma =
+            unsafe {device_driver::Dma::new(
+                memory::map::mmio::DMA_START,
+                DMA_CHANNELS,
+                memory::map::bus::RAM_ALIAS,
+            ) };
+
+/// The console UART: the PL011 (UART0), or the mini UART with `console_mini_uart`
+#[cfg(not(feature = "console_mini_uart"))]
+static CONSOLE_UART: device_driver::PL011Uart =
+            unsafe {device_driver::PL011Uart::new(
+                memory::map::mmio::PL011_UART_START,
+                exception::asynchronous::irq_map::PL011_UART,
+                // a console next to a dedicated loader link receives by interrupt
+                if cfg!(feature = "extra_loader_uart") {
+                    None
+                } else {
+                    pl011_rx_dma(0, memory::map::bus::PL011_UART_DR, &DMA)
+                },
+            ) };
+
+#[cfg(feature = "console_mini_uart")]
+static CONSOLE_UART: device_driver::MiniUart =
+            unsafe {device_driver::MiniUart::new(memory::map::mmio::MINI_UART_START, CORE_CLOCK_HZ) };
+
+/// Dedicated loader link on one of the BCM2711's extra PL011s (`loader_uartN` features)
+#[cfg(feature = "extra_loader_uart")]
+static LOADER_UART: device_driver::PL011Uart =
+            unsafe {device_driver::PL011Uart::new(
+                LOADER_UART_START,
+                // shared by all of the BCM2711's PL011s
+                exception::asynchronous::irq_map::PL011_UART,
+                pl011_rx_dma(LOADER_UART_NUMBER, LOADER_UART_DR, &DMA),
+            ) };
+
+/// VPU core clock, which drives the mini UART. `enable_uart=1` in config.txt pins it to this value.
+#[cfg(all(feature = "console_mini_uart", feature="bsp_rpi3"))]
+const CORE_CLOCK_HZ: u32 = 250_000_000;
+
+#[cfg(all(feature = "console_mini_uart", feature="bsp_rpi4"))]
+const CORE_CLOCK_HZ: u32 = 500_000_000;
+
+/// DMA channels the loader may use. The firmware leaves the ARM the channels in the device tree's
+/// `brcm,dma-channel-mask`: 0x7f35 on the RPi3 (it keeps 1, 3, 6 and 7) and 0x01f5 among the
+/// BCM2711's legacy channels (it keeps 1 and 3). 4 and 5 are in both.
+const DMA_CHANNELS: &[device_driver::DmaChannelConfig] = &[
+    device_driver::DmaChannelConfig { index: 4, irq_number: exception::asynchronous::irq_map::DMA4 },
+    device_driver::DmaChannelConfig { index: 5, irq_number: exception::asynchronous::irq_map::DMA5 },
+];
+
+//-----------------------------------------------
+//      Pin assignments
+//-----------------------------------------------
+
+/// Console UART pins: 14 (TX) and 15 (RX), alt0 for the PL011 and alt5 for the mini UART
+#[cfg(not(feature = "console_mini_uart"))]
+const CONSOLE_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 14, rx: 15, function: device_driver::PinFunction::Alt0 };
+
+#[cfg(feature = "console_mini_uart")]
+const CONSOLE_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 14, rx: 15, function: device_driver::PinFunction::Alt5 };
+
+// The extra PL011s of the BCM2711 are all on alt4.
+#[cfg(feature = "loader_uart2")]
+const LOADER_UART_NUMBER: usize = 2;
+#[cfg(feature = "loader_uart2")]
+const LOADER_UART_START: usize = memory::map::mmio::PL011_UART2_START;
+#[cfg(feature = "loader_uart2")]
+const LOADER_UART_DR: u32 = memory::map::bus::PL011_UART2_DR;
+#[cfg(feature = "loader_uart2")]
+const LOADER_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 0, rx: 1, function: device_driver::PinFunction::Alt4 };
+
+#[cfg(feature = "loader_uart3")]
+const LOADER_UART_NUMBER: usize = 3;
+#[cfg(feature = "loader_uart3")]
+const LOADER_UART_START: usize = memory::map::mmio::PL011_UART3_START;
+#[cfg(feature = "loader_uart3")]
+const LOADER_UART_DR: u32 = memory::map::bus::PL011_UART3_DR;
+#[cfg(feature = "loader_uart3")]
+const LOADER_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 4, rx: 5, function: device_driver::PinFunction::Alt4 };
+
+#[cfg(feature = "loader_uart4")]
+const LOADER_UART_NUMBER: usize = 4;
+#[cfg(feature = "loader_uart4")]
+const LOADER_UART_START: usize = memory::map::mmio::PL011_UART4_START;
+#[cfg(feature = "loader_uart4")]
+const LOADER_UART_DR: u32 = memory::map::bus::PL011_UART4_DR;
+#[cfg(feature = "loader_uart4")]
+const LOADER_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 8, rx: 9, function: device_driver::PinFunction::Alt4 };
+
+#[cfg(feature = "loader_uart5")]
+const LOADER_UART_NUMBER: usize = 5;
+#[cfg(feature = "loader_uart5")]
+const LOADER_UART_START: usize = memory::map::mmio::PL011_UART5_START;
+#[cfg(feature = "loader_uart5")]
+const LOADER_UART_DR: u32 = memory::map::bus::PL011_UART5_DR;
+#[cfg(feature = "loader_uart5")]
+const LOADER_UART_PINS: device_driver::UartPins =
+    device_driver::UartPins { tx: 12, rx: 13, function: device_driver::PinFunction::Alt4 };
+
+/// Strap pins sampled at boot (with pull-ups), header pins 40, 38 and 36, next to the grounds on
+/// 39 and 34. Each one jumpered to ground sets its bit of the boot mode code: 21 bit 0, 20 bit 1
+/// and 16 bit 2. All open boots normally.
+const BOOT_MODE_STRAP_PINS: [u32; 3] = [21, 20, 16];
+
+/// The boot mode of each code. Pin 21 alone, the one-jumper case, enters the monitor.
+const STRAPPED_BOOT_MODES: [BootMode; 8] = [
+    BootMode::Normal,
+    BootMode::Monitor,
+    BootMode::WaitForHost,
+    BootMode::BootCached,
+    BootMode::BootEmbedded,
+    // unassigned
+    BootMode::Normal,
+    BootMode::Normal,
+    BootMode::Normal,
+];
+
+/// Strap pin sampled at boot (with pull-up): jumpering it to ground moves the loader protocol
+/// from the dedicated loader UART back to the console UART.
+#[cfg(feature = "extra_loader_uart")]
+const LOADER_UART_STRAP_PIN: u32 = 26;
+
+/// How long a strap's pull-up gets to charge the pin (and whatever is attached to it) before the
+/// pin is sampled
+const STRAP_SETTLE_TIME: Duration = Duration::from_micros(100);
+
+/// The boot mode the strap pins selected, see [`sample_boot_mode`]
+static BOOT_MODE: InitStateLock<BootMode> = InitStateLock::new(BootMode::Normal);
 
 /// Returns the board's name (rpi3, rpi4)
 pub fn board_name() -> &'static str {
@@ -36,4 +204,75 @@ pub fn board_name() -> &'static str {
 
         "Raspberry pi 4"
     }
-}
\ No newline at end of file
+}
+
+/// The GPIO pins, and what it takes to configure them
+pub mod gpio {
+    pub use super::device_driver::{Event, PinFunction, Pull};
+
+    /// The GPIO driver. Pins are claimed before they are changed, see its methods.
+    pub fn driver() -> &'static super::device_driver::GPIO {
+        &super::GPIO
+    }
+}
+
+/// The board-wide microsecond time source. Unlike the core's generic timer, it is not affected by
+/// core frequency scaling.
+pub fn system_timer() -> &'static device_driver::SystemTimer {
+    &SYSTEM_TIMER
+}
+
+/// The DMA controller, for bulk copies
+pub fn dma() -> &'static device_driver::Dma {
+    &DMA
+}
+
+/// The boot mode the strap pins selected at power-on
+pub fn boot_mode() -> BootMode {
+    BOOT_MODE.read(|mode| *mode)
+}
+
+/// Sample the boot mode strap pins, and keep the mode they select for [`boot_mode`]. Called as
+/// soon as the GPIO driver is initialized, before anything else touches the pins.
+fn sample_boot_mode() {
+    let code = BOOT_MODE_STRAP_PINS
+        .iter()
+        .enumerate()
+        .filter(|(_, pin)| read_strap(**pin, "boot mode strap"))
+        .fold(0, |code, (bit, _)| code | 1 << bit);
+
+    BOOT_MODE.write(|mode| *mode = STRAPPED_BOOT_MODES[code]);
+}
+
+/// Claim a strap pin, enable its pull-up and sample it.
+/// Returns true if the pin is jumpered to ground.
+fn read_strap(pin: u32, owner: &'static str) -> bool {
+    if let Err(e) = GPIO.init_input_pin(pin, device_driver::Pull::Up, owner) {
+        panic!("Error setting up the {} pin: {}", owner, e);
+    }
+    // let the pull-up settle before sampling
+    time::time_manager().spin_for(STRAP_SETTLE_TIME);
+
+    match GPIO.read_level(pin) {
+        Ok(high) => !high,
+        Err(e) => panic!("Error sampling the {} pin: {}", owner, e),
+    }
+}
+
+/// How the PL011 carrying the loader link receives: by DMA with the `pl011_rx_dma` feature, by
+/// interrupt otherwise
+#[cfg(pl011)]
+const fn pl011_rx_dma(
+    uart: usize,
+    data_register_bus_addr: u32,
+    controller: &'static device_driver::Dma,
+) -> Option<device_driver::PL011RxDma> {
+    match device_driver::Dreq::pl011(uart) {
+        Some((_, dreq)) if cfg!(feature = "pl011_rx_dma") => Some(device_driver::PL011RxDma {
+            controller,
+            dreq,
+            data_register_bus_addr,
+        }),
+        _ => None,
+    }
+}
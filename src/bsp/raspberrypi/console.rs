@@ -7,7 +7,10 @@
 
 //! Write interface to UART data register
 
-use crate::{bsp::device_driver, console};
+use crate::{
+    bsp::device_driver, console, synchronization::interface::ReadWriteEx,
+    synchronization::InitStateLock,
+};
 use super::memory;
 use core::fmt;
 
@@ -28,18 +31,68 @@ use core::fmt;
 /// # Safety
 ///
 /// - Use only for printing during a panic.
+#[cfg(not(feature = "console_mini_uart"))]
 pub unsafe fn panic_console_out() -> impl fmt::Write {
-    let mut panic_gpio = device_driver::PanicGPIO::new(memory::map::GPIO_OFFSET);
-    let mut panic_uart = device_driver::PanicUart::new(memory::map::UART_OFFSET);
+    let mut panic_gpio = device_driver::PanicGPIO::new(memory::map::mmio::GPIO_START);
+    let mut panic_uart = device_driver::PanicUart::new(memory::map::mmio::PL011_UART_START);
 
-    panic_gpio.init_pl011_uart_pins();
+    // the panic GPIO instance starts with no pins claimed, so this can't fail
+    let _ = panic_gpio.init_uart_pins(&super::CONSOLE_UART_PINS, "panic console");
     panic_uart.init();
     panic_uart
 }
 
+/// Same as above, for boards that use the mini UART as console.
+///
+/// # Safety
+///
+/// - Use only for printing during a panic.
+#[cfg(feature = "console_mini_uart")]
+pub unsafe fn panic_console_out() -> impl fmt::Write {
+    let mut panic_gpio = device_driver::PanicGPIO::new(memory::map::mmio::GPIO_START);
+    let mut panic_uart =
+        device_driver::PanicMiniUart::new(memory::map::mmio::MINI_UART_START, super::CORE_CLOCK_HZ);
+
+    // the panic GPIO instance starts with no pins claimed, so this can't fail
+    let _ = panic_gpio.init_uart_pins(&super::CONSOLE_UART_PINS, "panic console");
+    panic_uart.init();
+    panic_uart
+}
+
+/// Return a reference to the console (PL011 by default, mini UART with `console_mini_uart`)
 pub fn console() -> &'static impl console::interface::All {
-    &super::PL011_UART
+    &super::CONSOLE_UART
 }
+
+/// The UART carrying the loader protocol. Defaults to the console, until
+/// [`select_loader_console`] picks the dedicated loader UART.
+static LOADER_CONSOLE: InitStateLock<&'static (dyn console::interface::All + Sync)> =
+    InitStateLock::new(&super::CONSOLE_UART);
+
+/// Return a reference to the UART carrying the loader protocol
+pub fn loader_console() -> &'static dyn console::interface::All {
+    LOADER_CONSOLE.read(|c| *c)
+}
+
+/// True if the loader protocol runs on the console UART, so host and human share it
+pub fn loader_shares_console() -> bool {
+    let loader = loader_console() as *const dyn console::interface::All as *const ();
+    let console = console() as *const _ as *const ();
+
+    core::ptr::eq(loader, console)
+}
+
+/// Move the loader protocol to the dedicated loader UART, unless its strap pin is pulled to
+/// ground.
+///
+/// Must be called after the GPIO driver is initialized.
+#[cfg(feature = "extra_loader_uart")]
+pub(super) fn select_loader_console() {
+    if !super::read_strap(super::LOADER_UART_STRAP_PIN, "loader UART strap") {
+        LOADER_CONSOLE.write(|c| *c = &super::LOADER_UART);
+    }
+}
+
 // This is the inner struct of the qemu output device.
 // it is lock-protected and thus can hold / save a state safely
 // struct QEMUOutputInner {
@@ -8,6 +8,31 @@
 
 //! Board specific processor code
 
+/// The core that runs the loader, `BOOT_CORE` at build time. boot.s parks the others, and gives
+/// it its own stack out of the core stacks (see link.ld). The firmware only starts core 0, which
+/// releases another boot core from the firmware's spin table.
 #[no_mangle]
 #[link_section = ".text._start_arguments"]
-pub static BOOT_CORE_ID: u64 = 0;
\ No newline at end of file
+pub static BOOT_CORE_ID: u64 = crate::config::BOOT_CORE;
+
+/// The spin table: a u64 release address per core, at SPIN_TABLE_START + 8 * core id. Parked
+/// cores jump to theirs once it's non-zero. Same place as the firmware's armstub, so images
+/// written for it work unchanged.
+#[no_mangle]
+#[link_section = ".text._start_arguments"]
+pub static SPIN_TABLE_START: u64 = 0xd8;
+
+/// Number of cores
+pub const NUM_CORES: usize = 4;
+
+/// What the generic timer counts at, in case the firmware leaves CNTFRQ_EL0 unset
+#[cfg(feature = "bsp_rpi3")]
+pub const TIMER_FREQUENCY_HZ: u64 = 19_200_000;
+
+#[cfg(feature = "bsp_rpi4")]
+pub const TIMER_FREQUENCY_HZ: u64 = 54_000_000;
+
+/// The spin table slot of a core
+pub fn spin_table_release_addr(core: usize) -> usize {
+    SPIN_TABLE_START as usize + 8 * core
+}
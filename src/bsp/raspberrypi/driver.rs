@@ -5,30 +5,78 @@
  * Author: Elad Matia (elad.matia@gmail.com)
  */
 
-use crate::driver::interface;
+use super::device_driver;
+use crate::driver::{self, DeviceDriverDescriptor};
 
-/// Driver manager for the raspberry Pi
-struct BSPDriverManager {
-   device_drivers: [&'static (dyn interface::DeviceDriver + Sync); 2] 
+/// What routing a UART's pins needs
+const UART_PINS_DEPENDENCIES: &[&str] = &[device_driver::GPIO::COMPATIBLE];
+
+/// Register the board's drivers with the driver manager. It initializes them after their
+/// dependencies, in registration order otherwise.
+pub fn register_drivers() -> Result<(), &'static str> {
+    let manager = driver::driver_manager();
+
+    manager.register_driver(DeviceDriverDescriptor::new(
+        &super::INTERRUPT_CONTROLLER,
+        &[],
+        None,
+        false,
+    ))?;
+    manager.register_driver(DeviceDriverDescriptor::new(
+        &super::GPIO,
+        &[],
+        Some(post_init_gpio),
+        false,
+    ))?;
+    manager.register_driver(DeviceDriverDescriptor::new(
+        &super::SYSTEM_TIMER,
+        &[],
+        Some(super::status::init_step_timer),
+        false,
+    ))?;
+    #[cfg(feature = "bsp_rpi3")]
+    manager.register_driver(DeviceDriverDescriptor::new(&super::MAILBOX, &[], None, false))?;
+    manager.register_driver(DeviceDriverDescriptor::new(&super::DMA, &[], None, false))?;
+    manager.register_driver(DeviceDriverDescriptor::new(
+        &super::CONSOLE_UART,
+        UART_PINS_DEPENDENCIES,
+        Some(post_init_console_uart),
+        false,
+    ))?;
+
+    // optional: without it, the loader protocol stays on the console UART
+    #[cfg(feature = "extra_loader_uart")]
+    manager.register_driver(DeviceDriverDescriptor::new(
+        &super::LOADER_UART,
+        UART_PINS_DEPENDENCIES,
+        Some(post_init_loader_uart),
+        true,
+    ))?;
+
+    Ok(())
 }
 
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
 
-/// GLOBAL, static driver manager
-static BSP_DRIVER_MANAGER: BSPDriverManager = BSPDriverManager {
-    device_drivers: [&super::GPIO, &super::PL011_UART]
-};
+/// Sample the strap pins while nothing else has touched the pins yet, then route the status LED
+fn post_init_gpio() -> Result<(), &'static str> {
+    super::sample_boot_mode();
 
-pub fn driver_manager() -> &'static impl interface::DeviceManager {
-    &BSP_DRIVER_MANAGER
+    super::status::init_status_led()
 }
 
-// Interface code for the device manager of the raspberry pi
-impl interface::DeviceManager for BSPDriverManager {
-    fn all_device_drivers(&self) -> &[&'static (dyn interface::DeviceDriver + Sync)] {
-        &self.device_drivers
-    }
+/// Route the console UART's pins
+fn post_init_console_uart() -> Result<(), &'static str> {
+    super::GPIO.init_uart_pins(&super::CONSOLE_UART_PINS, "console UART")
+}
+
+/// Route the loader UART's pins, then move the loader protocol over to it
+#[cfg(feature = "extra_loader_uart")]
+fn post_init_loader_uart() -> Result<(), &'static str> {
+    super::GPIO.init_uart_pins(&super::LOADER_UART_PINS, "loader UART")?;
+    super::console::select_loader_console();
 
-    fn post_device_driver_init(&self) {
-       super::GPIO.init_gpio_uart_pins();
-    }
-}
\ No newline at end of file
+    Ok(())
+}
@@ -0,0 +1,10 @@
+/*
+ * File: exception.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:48:12 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BSP exception handling
+
+pub mod asynchronous;
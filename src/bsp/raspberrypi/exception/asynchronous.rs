@@ -0,0 +1,56 @@
+/*
+ * File: asynchronous.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 11:50:31 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BSP asynchronous exception handling: the board's interrupt controller and IRQ numbers
+
+use crate::{bsp, exception};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The interrupt controller's IRQ number type
+pub use bsp::device_driver::IRQNumber;
+
+/// IRQ numbers of the devices the loader drives
+#[cfg(feature = "bsp_rpi3")]
+pub mod irq_map {
+    use super::bsp::device_driver::IRQNumber;
+
+    pub const SYSTEM_TIMER_C1: IRQNumber = IRQNumber::new(1);
+    pub const SYSTEM_TIMER_C3: IRQNumber = IRQNumber::new(3);
+    // DMA channel n is IRQ 16 + n.
+    pub const DMA4: IRQNumber = IRQNumber::new(20);
+    pub const DMA5: IRQNumber = IRQNumber::new(21);
+    #[cfg(pl011)]
+    pub const PL011_UART: IRQNumber = IRQNumber::new(57);
+}
+
+/// IRQ numbers of the devices the loader drives. VideoCore IRQ n is SPI 96 + n.
+#[cfg(feature = "bsp_rpi4")]
+pub mod irq_map {
+    use super::bsp::device_driver::IRQNumber;
+
+    pub const SYSTEM_TIMER_C1: IRQNumber = IRQNumber::new(97);
+    pub const SYSTEM_TIMER_C3: IRQNumber = IRQNumber::new(99);
+    pub const DMA4: IRQNumber = IRQNumber::new(116);
+    pub const DMA5: IRQNumber = IRQNumber::new(117);
+    // Shared by UART0 and UART2-5.
+    #[cfg(pl011)]
+    pub const PL011_UART: IRQNumber = IRQNumber::new(153);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the IRQ manager
+pub fn irq_manager() -> &'static impl exception::asynchronous::interface::IRQManager<
+    IRQNumberType = IRQNumber,
+> {
+    &super::super::INTERRUPT_CONTROLLER
+}
@@ -10,20 +10,91 @@
 // This is just a way to define the start address of UART and the GPIO. The trick is to figure out that the specified addresses are bus addresses
 // that need to be mapped physically.
 
+pub mod mmu;
+
+use core::{cell::UnsafeCell, ops::Range};
+
+// Symbols from the linker script.
+extern "Rust" {
+    static __loader_start: UnsafeCell<()>;
+    static __park_core_start: UnsafeCell<()>;
+    static __park_core_end_exclusive: UnsafeCell<()>;
+    static __psci_start: UnsafeCell<()>;
+    static __psci_end_exclusive: UnsafeCell<()>;
+
+    static __core_stacks_start: UnsafeCell<()>;
+    static __core_exception_stack_size: UnsafeCell<()>;
+    static __core_stack_guard_size: UnsafeCell<()>;
+    static __core_stack_size: UnsafeCell<()>;
+    static __core_stack_slot_size: UnsafeCell<()>;
+
+    static __heap_start: UnsafeCell<()>;
+    static __heap_end_exclusive: UnsafeCell<()>;
+
+    static __image_cache_data_start: UnsafeCell<()>;
+    static __image_cache_end_exclusive: UnsafeCell<()>;
+}
+
 pub mod map {
     pub const BOARD_DEFAULT_LOAD_ADDRESS: usize =        0x8_0000;
 
+    pub const SYSTEM_TIMER_OFFSET: usize = 0x0000_3000;
+    pub const DMA_OFFSET:          usize = 0x0000_7000;
+    #[cfg(feature = "bsp_rpi3")]
+    pub const PERIPHERAL_IC_OFFSET: usize = 0x0000_B200;
+    #[cfg(feature = "bsp_rpi3")]
+    pub const MAILBOX_OFFSET:      usize = 0x0000_B880;
+    #[cfg(feature = "psci")]
+    pub const WATCHDOG_OFFSET:     usize = 0x0010_0000;
     pub const GPIO_OFFSET:         usize = 0x0020_0000;
+    #[cfg(pl011)]
     pub const UART_OFFSET:         usize = 0x0020_1000;
+    #[cfg(feature = "console_mini_uart")]
+    pub const AUX_OFFSET:          usize = 0x0021_5000;
+
+    /// Bus addresses, as seen by the DMA engine
+    pub mod bus {
+        /// Start of the peripherals, whatever the ARM sees them at
+        #[cfg(pl011)]
+        pub const PERIPHERAL_START: u32 =       0x7E00_0000;
+        #[cfg(pl011)]
+        pub const PL011_UART_DR:    u32 = PERIPHERAL_START + super::UART_OFFSET as u32;
+        #[cfg(feature = "loader_uart2")]
+        pub const PL011_UART2_DR:   u32 = PL011_UART_DR + 0x400;
+        #[cfg(feature = "loader_uart3")]
+        pub const PL011_UART3_DR:   u32 = PL011_UART_DR + 0x600;
+        #[cfg(feature = "loader_uart4")]
+        pub const PL011_UART4_DR:   u32 = PL011_UART_DR + 0x800;
+        #[cfg(feature = "loader_uart5")]
+        pub const PL011_UART5_DR:   u32 = PL011_UART_DR + 0xA00;
+
+        /// RAM through the uncached alias, so the DMA engine bypasses the VideoCore L2
+        pub const RAM_ALIAS:        u32 =       0xC000_0000;
+    }
 
     /// Physical devices.
     #[cfg(feature = "bsp_rpi3")]
     pub mod mmio {
         use super::*;
 
-        pub const START:            usize =         0x3F00_0000;
-        pub const GPIO_START:       usize = START + GPIO_OFFSET;
-        pub const PL011_UART_START: usize = START + UART_OFFSET;
+        pub const START:               usize =         0x3F00_0000;
+        pub const SYSTEM_TIMER_START:  usize = START + SYSTEM_TIMER_OFFSET;
+        pub const DMA_START:           usize = START + DMA_OFFSET;
+        pub const PERIPHERAL_IC_START: usize = START + PERIPHERAL_IC_OFFSET;
+        pub const MAILBOX_START:       usize = START + MAILBOX_OFFSET;
+        #[cfg(feature = "psci")]
+        pub const WATCHDOG_START:      usize = START + WATCHDOG_OFFSET;
+        pub const GPIO_START:          usize = START + GPIO_OFFSET;
+        #[cfg(pl011)]
+        pub const PL011_UART_START:    usize = START + UART_OFFSET;
+        #[cfg(feature = "console_mini_uart")]
+        pub const MINI_UART_START:     usize = START + AUX_OFFSET;
+
+        // The BCM2836 "ARM local" block, outside the peripheral window.
+        pub const LOCAL_IC_START:      usize =         0x4000_0000;
+
+        // Includes the local block.
+        pub const END_INCLUSIVE:       usize =         0x4000_FFFF;
     }
 
     /// Physical devices.
@@ -31,9 +102,33 @@ pub mod map {
     pub mod mmio {
         use super::*;
 
-        pub const START:            usize =         0xFE00_0000;
-        pub const GPIO_START:       usize = START + GPIO_OFFSET;
-        pub const PL011_UART_START: usize = START + UART_OFFSET;
+        pub const START:               usize =         0xFE00_0000;
+        pub const SYSTEM_TIMER_START:  usize = START + SYSTEM_TIMER_OFFSET;
+        pub const DMA_START:           usize = START + DMA_OFFSET;
+        #[cfg(feature = "psci")]
+        pub const WATCHDOG_START:      usize = START + WATCHDOG_OFFSET;
+        pub const GPIO_START:          usize = START + GPIO_OFFSET;
+        #[cfg(pl011)]
+        pub const PL011_UART_START:    usize = START + UART_OFFSET;
+        #[cfg(feature = "console_mini_uart")]
+        pub const MINI_UART_START:     usize = START + AUX_OFFSET;
+
+        // The additional PL011s of the BCM2711, 0x200 apart after UART0.
+        #[cfg(feature = "loader_uart2")]
+        pub const PL011_UART2_START: usize = PL011_UART_START + 0x400;
+        #[cfg(feature = "loader_uart3")]
+        pub const PL011_UART3_START: usize = PL011_UART_START + 0x600;
+        #[cfg(feature = "loader_uart4")]
+        pub const PL011_UART4_START: usize = PL011_UART_START + 0x800;
+        #[cfg(feature = "loader_uart5")]
+        pub const PL011_UART5_START: usize = PL011_UART_START + 0xA00;
+
+        // The GIC-400, in the ARM local block (low peripheral mode addresses).
+        pub const GICD_START:        usize =         0xFF84_1000;
+        pub const GICC_START:        usize =         0xFF84_2000;
+
+        // Includes the GIC.
+        pub const END_INCLUSIVE:     usize =         0xFF84_FFFF;
     }
 }
 
@@ -41,3 +136,54 @@ pub mod map {
 pub fn board_default_load_address() -> *const u64 {
     map::BOARD_DEFAULT_LOAD_ADDRESS as _
 }
+
+/// Where images may be loaded: from the default load address up to the relocated loader
+pub fn loadable_range() -> Range<usize> {
+    map::BOARD_DEFAULT_LOAD_ADDRESS..unsafe { __loader_start.get() as usize }
+}
+
+/// The heap, above the loader and so out of [`loadable_range`]
+pub fn heap_region() -> Range<usize> {
+    unsafe { __heap_start.get() as usize..__heap_end_exclusive.get() as usize }
+}
+
+/// Where a copy of the last image received is kept, above the heap and out of the firmware's load
+/// window, so it survives a reset
+pub fn image_cache_region() -> Range<usize> {
+    unsafe { __image_cache_data_start.get() as usize..__image_cache_end_exclusive.get() as usize }
+}
+
+/// The page(s) of the relocated loader where secondary cores wait for the image, as
+/// (start, size). The image mustn't overwrite them before it has released the cores.
+#[cfg(not(feature = "psci"))]
+pub fn park_core_region() -> (usize, usize) {
+    unsafe {
+        let start = __park_core_start.get() as usize;
+        (start, __park_core_end_exclusive.get() as usize - start)
+    }
+}
+
+/// The pages of the relocated loader holding the resident PSCI handler, as (start, size)
+#[cfg(feature = "psci")]
+pub fn psci_region() -> (usize, usize) {
+    unsafe {
+        let start = __psci_start.get() as usize;
+        (start, __psci_end_exclusive.get() as usize - start)
+    }
+}
+
+/// The stack of `core`, growing down from the end of the range to the guard page below it
+pub fn core_stack(core: usize) -> Range<usize> {
+    let guard = core_stack_guard(core);
+    unsafe { guard.end..guard.end + __core_stack_size.get() as usize }
+}
+
+/// The guard page below the stack of `core`, which the MMU leaves unmapped
+pub fn core_stack_guard(core: usize) -> Range<usize> {
+    unsafe {
+        let start = __core_stacks_start.get() as usize
+            + core * __core_stack_slot_size.get() as usize
+            + __core_exception_stack_size.get() as usize;
+        start..start + __core_stack_guard_size.get() as usize
+    }
+}
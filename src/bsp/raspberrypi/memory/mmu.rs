@@ -0,0 +1,55 @@
+/*
+ * File: mmu.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:38:55 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! BSP Memory Management Unit: the board's memory map, as the MMU sees it.
+
+use super::map as memory_map;
+use crate::memory::mmu::*;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The address space the translation tables cover. On the RPi3, 2 GiB to reach the local
+/// peripherals at 0x4000_0000.
+#[cfg(feature = "bsp_rpi3")]
+pub type KernelAddrSpace = AddressSpace<{ 2 * 1024 * 1024 * 1024 }>;
+
+/// The address space the translation tables cover, up to the peripherals at the top of 4 GiB
+#[cfg(feature = "bsp_rpi4")]
+pub type KernelAddrSpace = AddressSpace<{ 4 * 1024 * 1024 * 1024 }>;
+
+const NUM_MEM_RANGES: usize = 1;
+
+/// The board's memory map: the peripherals are device memory, everything else is RAM.
+pub static LAYOUT: KernelVirtualLayout<NUM_MEM_RANGES> = KernelVirtualLayout::new(
+    KernelAddrSpace::SIZE - 1,
+    [TranslationDescriptor {
+        name: "Device MMIO",
+        range: memory_map::mmio::START..=memory_map::mmio::END_INCLUSIVE,
+        attribute_fields: AttributeFields {
+            mem_attributes: MemAttributes::Device,
+            acc_perms: AccessPermissions::ReadWrite,
+            execute_never: true,
+        },
+    }],
+);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the virtual memory layout
+pub fn virt_mem_layout() -> &'static KernelVirtualLayout<NUM_MEM_RANGES> {
+    &LAYOUT
+}
+
+/// Whether the MMU leaves the page at `virt_addr` unmapped, so touching it faults: the guard pages
+/// below the core stacks
+pub fn virt_addr_unmapped(virt_addr: usize) -> bool {
+    crate::cpu::stack::guarded_core(virt_addr).is_some()
+}
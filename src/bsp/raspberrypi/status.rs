@@ -0,0 +1,92 @@
+/*
+ * File: status.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 6:48:20 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Board status LED: the activity LED by default.
+//!
+//! On the RPi3 the activity LED sits on the firmware's GPIO expander and is switched through the
+//! mailbox, on the RPi4 it is a regular GPIO. Each board only has the kind of LED it needs, on the
+//! RPi4 any other GPIO (e.g. an external LED) can be used by changing `STATUS_LED`.
+
+use super::device_driver::TimerChannel;
+use crate::status;
+use core::time::Duration;
+
+/// Where the status LED is
+enum StatusLed {
+    /// A regular GPIO, LED on when high
+    #[cfg(feature = "bsp_rpi4")]
+    Gpio(u32),
+    /// A GPIO owned by the firmware, switched through the mailbox
+    #[cfg(feature = "bsp_rpi3")]
+    Mailbox(u32),
+}
+
+/// The activity LED, pin 130 of the firmware's GPIO expander
+#[cfg(feature = "bsp_rpi3")]
+const STATUS_LED: StatusLed = StatusLed::Mailbox(130);
+
+/// The activity LED
+#[cfg(feature = "bsp_rpi4")]
+const STATUS_LED: StatusLed = StatusLed::Gpio(42);
+
+/// The GPIO owner name of the status LED pin
+#[cfg(feature = "bsp_rpi4")]
+const STATUS_LED_OWNER: &str = "status LED";
+
+/// The system timer channel ending the pattern's steps
+const STEP_TIMER: TimerChannel = TimerChannel::C1;
+
+struct BoardLed;
+
+static BOARD_LED: BoardLed = BoardLed;
+
+/// Route the status LED pin, if it is a regular GPIO. Part of the GPIO driver's post-init
+/// callback.
+pub(super) fn init_status_led() -> Result<(), &'static str> {
+    match STATUS_LED {
+        #[cfg(feature = "bsp_rpi4")]
+        StatusLed::Gpio(pin) => super::GPIO.init_output_pin(pin, false, STATUS_LED_OWNER),
+        #[cfg(feature = "bsp_rpi3")]
+        StatusLed::Mailbox(_) => Ok(()),
+    }
+}
+
+/// Have the step timer's IRQ move the pattern on. The system timer driver's post-init callback.
+pub(super) fn init_step_timer() -> Result<(), &'static str> {
+    super::SYSTEM_TIMER.set_on_match(STEP_TIMER, status::next_step);
+
+    Ok(())
+}
+
+/// Interrupt once the current step's `duration` is over, see [`status::next_step`]
+pub fn start_step_timer(duration: Duration) {
+    // the steps are far shorter than the ~71 minutes the timer can count
+    let _ = super::SYSTEM_TIMER.arm(STEP_TIMER, duration);
+}
+
+/// Return a reference to the status LED
+pub fn status_led() -> &'static impl status::interface::Led {
+    &BOARD_LED
+}
+
+impl status::interface::Led for BoardLed {
+    fn set(&self, on: bool) {
+        match STATUS_LED {
+            // only fails if the pin wasn't routed, and then there is no LED to switch
+            #[cfg(feature = "bsp_rpi4")]
+            StatusLed::Gpio(pin) => {
+                let _ = super::GPIO.set_level(pin, on, STATUS_LED_OWNER);
+            }
+            // Older firmware (and QEMU) doesn't know the tag. Nothing useful to do about it, the
+            // LED is only a hint.
+            #[cfg(feature = "bsp_rpi3")]
+            StatusLed::Mailbox(pin) => {
+                let _ = super::MAILBOX.set_gpio_state(pin, on);
+            }
+        }
+    }
+}
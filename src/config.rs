@@ -0,0 +1,13 @@
+/*
+ * File: config.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 10:14:09 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Build-time configuration.
+//!
+//! Generated by build.rs from environment variables, which the Makefile exports (e.g.
+//! `make AUTOBOOT_SECS=10` or `make BOOT_CORE=2`).
+
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
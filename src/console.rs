@@ -7,6 +7,7 @@
 
 pub mod interface {
     pub use core::fmt;
+    use core::time::Duration;
 
     /// Console write functions
     pub trait Write {
@@ -24,6 +25,22 @@ pub mod interface {
         fn read_char(&self) -> char {
             ' '
         }
+        /// Read one character if one is available, don't block
+        fn try_read_char(&self) -> Option<char>;
+        /// Read one character, give up after `timeout`
+        fn read_char_timeout(&self, timeout: Duration) -> Option<char>;
+        /// Fill `buf` with bytes, give up once nothing arrived for `timeout`.
+        /// Returns how many bytes were read.
+        fn read_bytes_timeout(&self, buf: &mut [u8], timeout: Duration) -> usize {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                match self.read_char_timeout(timeout) {
+                    Some(c) => *byte = c as u8,
+                    None => return i,
+                }
+            }
+
+            buf.len()
+        }
         /// Clear RX buffers
         fn clear_rx(&self);
     }
@@ -37,8 +54,13 @@ pub mod interface {
         fn chars_read(&self) -> usize {
             0
         }
+        /// returns the number of received characters lost because they weren't read in time
+        fn rx_overruns(&self) -> usize {
+            0
+        }
     }
 
-    /// trait alias: All for output interface that needs to implement
-    pub trait All = Read + Write + Statistics;
+    /// All for output interface that needs to implement.
+    /// A real trait rather than a trait alias, so it can be used as `dyn All`.
+    pub trait All: Read + Write + Statistics {}
 }
\ No newline at end of file
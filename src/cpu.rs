@@ -8,10 +8,11 @@
 //! Processor code
 
 mod boot;
+pub mod smp;
+pub mod stack;
 
 #[cfg(target_arch = "aarch64")]
 #[path = "_arch/aarch64/cpu.rs"]
 mod arch_cpu;
 
-pub use arch_cpu::{nop, wait_forever};
-pub use arch_cpu::spin_for_cycles;
\ No newline at end of file
+pub use arch_cpu::{core_id, nop, wake_waiting_cores};
\ No newline at end of file
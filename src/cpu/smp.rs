@@ -0,0 +1,80 @@
+/*
+ * File: smp.rs
+ * Project: RpiOS
+ * File Created: Thursday, 22nd October 2026 10:14:05 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! The secondary cores.
+//!
+//! boot.s releases them into the park loop of the relocated loader, where they wait on the spin
+//! table for an image (see [`crate::loader::session`]) or for the resident PSCI handler. Each
+//! core clears its spin table slot once it's there, so the loader can tell which cores really
+//! arrived: a core that didn't (the firmware never started it, or it went astray) can't be given
+//! anything to run, and the image isn't told about it.
+
+use super::core_id;
+use crate::{
+    bsp, memory,
+    synchronization::{interface::ReadWriteEx, InitStateLock},
+    time::Deadline,
+};
+use core::time::Duration;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// How long the secondary cores get to reach the park loop
+const PARK_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The cores that arrived in the park loop, by core
+static PARKED: InitStateLock<[bool; bsp::cpu::NUM_CORES]> =
+    InitStateLock::new([false; bsp::cpu::NUM_CORES]);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Wait for the secondary cores to arrive in the park loop, and note which did.
+///
+/// # Safety
+///
+/// - Init only, before anything is written to the spin table.
+pub unsafe fn init() {
+    let deadline = Deadline::after(PARK_TIMEOUT);
+
+    let parked = loop {
+        let parked = arrived();
+        let all = (0..bsp::cpu::NUM_CORES).all(|core| core == core_id() || parked[core]);
+
+        if all || deadline.has_passed() {
+            break parked;
+        }
+    };
+
+    PARKED.write(|cores| *cores = parked);
+}
+
+/// True if `core` waits in the park loop, for an image or the PSCI handler
+pub fn is_parked(core: usize) -> bool {
+    PARKED.read(|cores| cores.get(core).copied().unwrap_or(false))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// The cores whose spin table slot is clear again
+fn arrived() -> [bool; bsp::cpu::NUM_CORES] {
+    // the cores write with their caches off, straight to RAM
+    memory::clean_invalidate_dcache_range(
+        bsp::cpu::spin_table_release_addr(0),
+        8 * bsp::cpu::NUM_CORES,
+    );
+
+    core::array::from_fn(|core| {
+        let slot = bsp::cpu::spin_table_release_addr(core) as *const u64;
+        core != core_id() && unsafe { core::ptr::read_volatile(slot) } == 0
+    })
+}
@@ -0,0 +1,68 @@
+/*
+ * File: stack.rs
+ * Project: RpiOS
+ * File Created: Wednesday, 21st October 2026 3:12:40 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Stack overflow detection.
+//!
+//! Each core has a stack of its own, with a guard page below it (see link.ld). While the MMU is
+//! on, the guard page is unmapped: running into it faults, and the exception handler, which has a
+//! stack of its own, reports the overflow. While the MMU is off, before it's turned on and around
+//! the handoff, a canary at the bottom of each stack catches it instead. The canaries are checked
+//! on exceptions and right before the handoff.
+
+use crate::bsp;
+use core::ptr;
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Written to the bottom of each stack, an overflow overwrites it
+const CANARY: u64 = 0x57ac_c0de_57ac_c0de;
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Write the canaries.
+///
+/// # Safety
+///
+/// - Only the boot core may run, before anything else looks at the canaries.
+pub unsafe fn init() {
+    for core in 0..bsp::cpu::NUM_CORES {
+        ptr::write_volatile(canary(core), CANARY);
+    }
+}
+
+/// The core whose stack overflowed: `fault_addr` is in its guard page, or its canary is gone
+pub fn overflowed_core(fault_addr: Option<usize>) -> Option<usize> {
+    if let Some(core) = fault_addr.and_then(guarded_core) {
+        return Some(core);
+    }
+
+    (0..bsp::cpu::NUM_CORES).find(|core| unsafe { ptr::read_volatile(canary(*core)) } != CANARY)
+}
+
+/// Panic if a stack overflowed
+pub fn check() {
+    if let Some(core) = overflowed_core(None) {
+        panic!("Stack overflow on core {}", core);
+    }
+}
+
+/// The core whose guard page holds `addr`
+pub fn guarded_core(addr: usize) -> Option<usize> {
+    (0..bsp::cpu::NUM_CORES).find(|core| bsp::memory::core_stack_guard(*core).contains(&addr))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+fn canary(core: usize) -> *mut u64 {
+    bsp::memory::core_stack(core).start as *mut u64
+}
@@ -5,27 +5,348 @@
 * Author: Elad Matia (elad.matia@gmail.com)
 */
 
-/// Driver-related traits (DeviceDriver, Driver manager)
+//! Driver support: the device driver trait, and the driver manager drivers are registered with
+//!
+//! Drivers name what they need initialized before them by its `compatible()` string, and the
+//! manager initializes them in an order that respects that, registration order otherwise. A
+//! driver that can't be initialized (it fails, or something it depends on isn't there) stops the
+//! boot, unless it was registered as optional: then it is skipped, and so is everything depending
+//! on it.
+
+use crate::{
+    println,
+    synchronization::{interface::ReadWriteEx, InitStateLock},
+};
+use core::fmt;
+
+/// Driver-related traits
 pub mod interface {
-    /// Device driver trait - each driver has to implement this 
+    /// Device driver trait - each driver has to implement this
     pub trait DeviceDriver {
         /// Return a string identifying the driver
         fn compatible(&self) -> &'static str;
 
+        /// The drivers (by `compatible()`) that must be initialized before this one
+        fn dependencies(&self) -> &'static [&'static str] {
+            &[]
+        }
+
         /// Called by kernel on startup to initialize the driver.
         /// Devices can only be used after their driver has been initialized
         fn init(&self) -> Result<(), &'static str> {
             Ok(())
         }
+
+        /// Called by kernel after all drivers were initialized, to register the driver's IRQ
+        /// handler (if it has one) with the interrupt controller and enable its interrupt.
+        fn register_and_enable_irq_handler(&'static self) -> Result<(), &'static str> {
+            Ok(())
+        }
+
+        /// Called by the loader right before the handoff, to quiesce the device: no interrupts,
+        /// no DMA, nothing the image doesn't expect. The device may stay usable, e.g. a UART for
+        /// the image's early console.
+        fn shutdown(&self) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Most drivers a board can register
+const NUM_DRIVERS: usize = 10;
+
+/// Where a registered driver is at
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum DriverState {
+    Registered,
+    Initialized,
+    Skipped,
+}
+
+struct DriverManagerInner {
+    next_index: usize,
+    descriptors: [Option<DeviceDriverDescriptor>; NUM_DRIVERS],
+    states: [DriverState; NUM_DRIVERS],
+
+    /// Indices into `descriptors`, in init order. Only the initialized drivers.
+    init_order: [usize; NUM_DRIVERS],
+    num_initialized: usize,
+}
+
+/// Prints the dependency cycle a driver left uninitialized is in, or depends on
+struct DependencyCycle<'a> {
+    inner: &'a DriverManagerInner,
+    start: usize,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Called once the driver is initialized, e.g. for board setup that needs the device
+pub type DeviceDriverPostInitCallback = fn() -> Result<(), &'static str>;
+
+/// A driver as the board registers it
+#[derive(Copy, Clone)]
+pub struct DeviceDriverDescriptor {
+    device_driver: &'static (dyn interface::DeviceDriver + Sync),
+
+    /// What the post-init callback needs on top of the driver's own dependencies
+    dependencies: &'static [&'static str],
+
+    post_init_callback: Option<DeviceDriverPostInitCallback>,
+
+    /// Skip the driver if it can't be initialized, instead of stopping the boot
+    optional: bool,
+}
+
+/// The registered drivers, in registration order. The BSP registers them during init.
+pub struct DriverManager {
+    inner: InitStateLock<DriverManagerInner>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static DRIVER_MANAGER: DriverManager = DriverManager::new();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+impl DriverManagerInner {
+    const fn new() -> Self {
+        Self {
+            next_index: 0,
+            descriptors: [None; NUM_DRIVERS],
+            states: [DriverState::Registered; NUM_DRIVERS],
+            init_order: [0; NUM_DRIVERS],
+            num_initialized: 0,
+        }
+    }
+
+    fn descriptor(&self, index: usize) -> &DeviceDriverDescriptor {
+        self.descriptors[index].as_ref().unwrap()
+    }
+
+    /// The index of the first driver registered as `compatible`
+    fn find(&self, compatible: &str) -> Option<usize> {
+        (0..self.next_index).find(|i| self.descriptor(*i).device_driver.compatible() == compatible)
+    }
+
+    /// The dependencies of a driver: the driver's own, then its post-init callback's
+    fn dependencies(&self, index: usize) -> impl Iterator<Item = &'static str> {
+        let descriptor = self.descriptor(index);
+
+        descriptor
+            .device_driver
+            .dependencies()
+            .iter()
+            .chain(descriptor.dependencies.iter())
+            .copied()
+    }
+
+    /// A dependency of a driver that is registered but not settled yet, i.e. holding it back
+    fn registered_dependency(&self, index: usize) -> Option<usize> {
+        self.dependencies(index)
+            .filter_map(|compatible| self.find(compatible))
+            .find(|dep| self.states[*dep] == DriverState::Registered)
+    }
+
+    /// The first driver, in registration order, whose dependencies are all settled
+    fn next_to_init(&self) -> Option<usize> {
+        (0..self.next_index).find(|i| {
+            self.states[*i] == DriverState::Registered && self.registered_dependency(*i).is_none()
+        })
+    }
+
+    /// Initialize a driver whose dependencies are settled, and run its post-init callback
+    fn init_driver(&mut self, index: usize) {
+        let missing = self.dependencies(index).find(|compatible| {
+            self.find(compatible).map(|dep| self.states[dep]) != Some(DriverState::Initialized)
+        });
+
+        let result = match missing {
+            Some(compatible) => {
+                self.report_unavailable(
+                    index,
+                    format_args!("needs {}, which isn't there", compatible),
+                );
+                DriverState::Skipped
+            }
+            None => match self.descriptor(index).init() {
+                Ok(()) => DriverState::Initialized,
+                Err(e) => {
+                    self.report_unavailable(index, format_args!("{}", e));
+                    DriverState::Skipped
+                }
+            },
+        };
+
+        self.states[index] = result;
+        if result == DriverState::Initialized {
+            self.init_order[self.num_initialized] = index;
+            self.num_initialized += 1;
+        }
+    }
+
+    /// A driver can't be initialized: stop the boot, unless it is optional and can be skipped
+    fn report_unavailable(&self, index: usize, reason: fmt::Arguments) {
+        let descriptor = self.descriptor(index);
+        let compatible = descriptor.device_driver.compatible();
+
+        if !descriptor.optional {
+            panic!("Can't initialize {} driver: {}", compatible, reason);
+        }
+
+        println!("Skipping optional {} driver: {}", compatible, reason);
+    }
+
+    /// The initialized drivers, in init order
+    fn initialized(&self) -> impl DoubleEndedIterator<Item = &DeviceDriverDescriptor> {
+        self.init_order[..self.num_initialized]
+            .iter()
+            .map(|index| self.descriptor(*index))
+    }
+}
+
+impl DeviceDriverDescriptor {
+    fn init(&self) -> Result<(), &'static str> {
+        self.device_driver.init()?;
+
+        match self.post_init_callback {
+            Some(callback) => callback(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for DependencyCycle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner;
+        let next = |index| inner.registered_dependency(index).unwrap();
+
+        // Every driver left uninitialized waits for another one, so following them long enough
+        // ends up going round the cycle.
+        let mut first = self.start;
+        for _ in 0..inner.next_index {
+            first = next(first);
+        }
+
+        write!(f, "{}", inner.descriptor(first).device_driver.compatible())?;
+        let mut index = next(first);
+        loop {
+            write!(f, " -> {}", inner.descriptor(index).device_driver.compatible())?;
+            if index == first {
+                return Ok(());
+            }
+            index = next(index);
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the global DriverManager
+pub fn driver_manager() -> &'static DriverManager {
+    &DRIVER_MANAGER
+}
+
+impl DeviceDriverDescriptor {
+    /// Create an instance. `dependencies` are what `post_init_callback` needs initialized, on top
+    /// of what the driver itself declares.
+    pub const fn new(
+        device_driver: &'static (dyn interface::DeviceDriver + Sync),
+        dependencies: &'static [&'static str],
+        post_init_callback: Option<DeviceDriverPostInitCallback>,
+        optional: bool,
+    ) -> Self {
+        Self {
+            device_driver,
+            dependencies,
+            post_init_callback,
+            optional,
+        }
+    }
+}
+
+impl DriverManager {
+    /// Create an instance
+    pub const fn new() -> Self {
+        Self {
+            inner: InitStateLock::new(DriverManagerInner::new()),
+        }
+    }
+
+    /// Register a driver. Only possible during init.
+    pub fn register_driver(&self, descriptor: DeviceDriverDescriptor) -> Result<(), &'static str> {
+        self.inner.write(|inner| {
+            let slot = inner
+                .descriptors
+                .get_mut(inner.next_index)
+                .ok_or("too many drivers, raise NUM_DRIVERS")?;
+
+            *slot = Some(descriptor);
+            inner.next_index += 1;
+            Ok(())
+        })
+    }
+
+    /// Initialize the registered drivers, each after its dependencies, and run their post-init
+    /// callbacks. Panics if a driver that isn't optional can't be initialized, or is caught in a
+    /// dependency cycle.
+    pub fn init_drivers(&self) {
+        self.inner.write(|inner| {
+            while let Some(index) = inner.next_to_init() {
+                inner.init_driver(index);
+            }
+
+            // what's left waits for a driver that waits for it in turn
+            for index in 0..inner.next_index {
+                if inner.states[index] == DriverState::Registered {
+                    let cycle = DependencyCycle { inner, start: index };
+                    inner.report_unavailable(index, format_args!("dependency cycle {}", cycle));
+                }
+            }
+            for state in inner.states[..inner.next_index].iter_mut() {
+                if *state == DriverState::Registered {
+                    *state = DriverState::Skipped;
+                }
+            }
+        });
+    }
+
+    /// Register and enable the IRQ handlers of the initialized drivers. Panics if one fails.
+    pub fn register_irq_handlers(&self) {
+        self.inner.read(|inner| {
+            for descriptor in inner.initialized() {
+                let driver = descriptor.device_driver;
+
+                if let Err(e) = driver.register_and_enable_irq_handler() {
+                    panic!("Error registering IRQ handler of {}: {}", driver.compatible(), e);
+                }
+            }
+        });
     }
 
-    /// Each BSP should implement its own device manager.
-    /// Only one global instance should exist
-    pub trait DeviceManager {
-        /// Return a slice of references to all initialized drivers.
-        fn all_device_drivers(&self) -> &[&'static (dyn DeviceDriver + Sync)];
+    /// Shut the initialized drivers down before the handoff, in reverse init order, so none goes
+    /// before a driver depending on it. A failing driver is reported, the others still shut down.
+    pub fn shutdown_all(&self) {
+        self.inner.read(|inner| {
+            for descriptor in inner.initialized().rev() {
+                let driver = descriptor.device_driver;
 
-        /// post-init driver work. useful when drivers depends on other driver to work
-        fn post_device_driver_init(&self);
+                if let Err(e) = driver.shutdown() {
+                    println!("Error shutting down {} driver: {}", driver.compatible(), e);
+                }
+            }
+        });
     }
-}
\ No newline at end of file
+}
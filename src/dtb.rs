@@ -0,0 +1,671 @@
+/*
+ * File: dtb.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 10:24:37 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! The device tree (DTB) handed to the image.
+//!
+//! The firmware passes the board's device tree to the loader. The loader keeps a copy of it,
+//! applies its fixups right before the handoff and passes the copy's address to the image in x0,
+//! as the arm64 boot protocol asks.
+//!
+//! Only as much of the format as the fixups need (devicetree specification, chapter 5): finding
+//! nodes by path, setting properties, adding memory reservations. The copy keeps the blocks in the
+//! usual order (memory reservations, structure, strings) without gaps, so an edit is inserting or
+//! removing bytes in the middle and moving the blocks behind it.
+
+use crate::{
+    bsp, cpu, loader, println,
+    synchronization::{interface::Mutex, NullLock},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+/// Structure block tokens
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+
+/// Header fields, big-endian u32 each
+mod header {
+    pub const MAGIC: usize = 0;
+    pub const TOTALSIZE: usize = 4;
+    pub const OFF_DT_STRUCT: usize = 8;
+    pub const OFF_DT_STRINGS: usize = 12;
+    pub const OFF_MEM_RSVMAP: usize = 16;
+    pub const VERSION: usize = 20;
+    pub const SIZE_DT_STRINGS: usize = 32;
+    pub const SIZE_DT_STRUCT: usize = 36;
+
+    pub const SIZE: usize = 40;
+}
+
+/// Room for the firmware's device tree and the fixups. The RPi4's is ~55 KiB.
+const CAPACITY: usize = 128 * 1024;
+
+/// The loader's copy of the device tree
+#[repr(C, align(8))]
+struct Fdt {
+    buf: [u8; CAPACITY],
+
+    /// Why there is no device tree, if there isn't
+    state: Result<(), &'static str>,
+}
+
+static DTB: NullLock<Fdt> = NullLock::new(Fdt::new());
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Copy the device tree the firmware passed at `boot_dtb_addr` (0 for none). Without one, the
+/// image gets none either.
+pub fn init(boot_dtb_addr: usize) {
+    DTB.lock(|fdt| fdt.state = unsafe { fdt.load(boot_dtb_addr) });
+}
+
+/// Address and size of the loader's copy of the device tree, or why there is none
+pub fn info() -> Result<(usize, usize), &'static str> {
+    DTB.lock(|fdt| {
+        fdt.state
+            .map(|_| (fdt.buf.as_ptr() as usize, fdt.total_size()))
+    })
+}
+
+/// Apply the fixups, and return the address of the device tree to pass to the image (0 for none)
+pub fn prepare_handoff() -> usize {
+    DTB.lock(|fdt| {
+        if let Err(e) = fdt.state {
+            println!("No device tree for the image: {}", e);
+            return 0;
+        }
+
+        #[cfg(not(feature = "psci"))]
+        if let Err(e) = fixup_spin_table(fdt) {
+            println!("Device tree: can't describe the spin table: {}", e);
+        }
+
+        #[cfg(feature = "psci")]
+        if let Err(e) = fixup_psci(fdt) {
+            println!("Device tree: can't describe the PSCI handler: {}", e);
+        }
+
+        if let Err(e) = fixup_absent_cores(fdt) {
+            println!("Device tree: can't hide the cores that didn't park: {}", e);
+        }
+
+        if let Err(e) = fixup_session(fdt) {
+            println!("Device tree: can't hide the other cores' images: {}", e);
+        }
+
+        if let Err(e) = fixup_image_cache(fdt) {
+            println!("Device tree: can't reserve the image cache: {}", e);
+        }
+
+        fdt.buf.as_ptr() as usize
+    })
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Call `f` with each cpu node of a core the loader knows about, and the core's id
+fn for_each_cpu_node(
+    fdt: &mut Fdt,
+    mut f: impl FnMut(&mut Fdt, usize, usize) -> Result<(), &'static str>,
+) -> Result<(), &'static str> {
+    let cpus = fdt.find_node("/cpus").ok_or("no /cpus node")?;
+
+    let mut index = 0;
+    // edits happen inside the cpu nodes, the /cpus offset stays valid
+    while let Some(cpu) = fdt.child(cpus, index) {
+        index += 1;
+
+        if !fdt.node_name(cpu).starts_with(b"cpu@") {
+            continue;
+        }
+        let core = fdt.property_u32(cpu, "reg").ok_or("cpu node without reg")? as usize;
+        if core >= bsp::cpu::NUM_CORES {
+            continue;
+        }
+
+        f(fdt, cpu, core)?;
+    }
+
+    Ok(())
+}
+
+/// Point the cpu nodes at the spin table the secondary cores are parked on, and keep the image
+/// from overwriting it, or the loop they wait in, before it released them
+#[cfg(not(feature = "psci"))]
+fn fixup_spin_table(fdt: &mut Fdt) -> Result<(), &'static str> {
+    for_each_cpu_node(fdt, |fdt, cpu, core| {
+        let release_addr = bsp::cpu::spin_table_release_addr(core) as u64;
+        fdt.set_property_str(cpu, "enable-method", "spin-table")?;
+        fdt.set_property(cpu, "cpu-release-addr", &release_addr.to_be_bytes())
+    })?;
+
+    let (park_start, park_size) = bsp::memory::park_core_region();
+    fdt.add_mem_reservation(
+        bsp::cpu::spin_table_release_addr(0) as u64,
+        8 * bsp::cpu::NUM_CORES as u64,
+    )?;
+    fdt.add_mem_reservation(park_start as u64, park_size as u64)
+}
+
+/// Disable the cpu nodes of the cores that never reached the park loop, nothing can start them
+fn fixup_absent_cores(fdt: &mut Fdt) -> Result<(), &'static str> {
+    for_each_cpu_node(fdt, |fdt, node, core| {
+        if core == cpu::core_id() || cpu::smp::is_parked(core) {
+            return Ok(());
+        }
+        fdt.set_property_str(node, "status", "disabled")
+    })
+}
+
+/// Keep the image away from the cores the session starts images of their own on, and from their
+/// images
+fn fixup_session(fdt: &mut Fdt) -> Result<(), &'static str> {
+    let images = loader::session::secondary_images();
+
+    for_each_cpu_node(fdt, |fdt, cpu, core| match images[core] {
+        Some(_) => fdt.set_property_str(cpu, "status", "disabled"),
+        None => Ok(()),
+    })?;
+
+    for image in images.iter().flatten() {
+        fdt.add_mem_reservation(image.addr as u64, image.size as u64)?;
+    }
+
+    Ok(())
+}
+
+/// Keep the image off the cached copy of itself, so the copy is still there after a reset
+fn fixup_image_cache(fdt: &mut Fdt) -> Result<(), &'static str> {
+    match loader::image_cache_in_use() {
+        Some((start, size)) => fdt.add_mem_reservation(start as u64, size as u64),
+        None => Ok(()),
+    }
+}
+
+/// Describe the resident PSCI handler: the cpu nodes use it, a `/psci` node says how to call it,
+/// and a `/reserved-memory` node keeps the image off its pages
+#[cfg(feature = "psci")]
+fn fixup_psci(fdt: &mut Fdt) -> Result<(), &'static str> {
+    use core::fmt::Write;
+
+    for_each_cpu_node(fdt, |fdt, cpu, _| fdt.set_property_str(cpu, "enable-method", "psci"))?;
+
+    // the root node is first, adding subnodes doesn't move it
+    let root = fdt.find_node("/").ok_or("no root node")?;
+
+    let psci = match fdt.find_node("/psci") {
+        Some(node) => node,
+        None => fdt.add_node(root, "psci")?,
+    };
+    fdt.set_property_str(psci, "compatible", "arm,psci-0.2")?;
+    fdt.set_property_str(psci, "method", crate::psci::CONDUIT.method())?;
+
+    let reserved_memory = match fdt.find_node("/reserved-memory") {
+        Some(node) => node,
+        None => {
+            // an empty ranges: the same address space as the root's
+            let address_cells = fdt.property_u32(root, "#address-cells").unwrap_or(2);
+            let size_cells = fdt.property_u32(root, "#size-cells").unwrap_or(1);
+
+            let node = fdt.add_node(root, "reserved-memory")?;
+            fdt.set_property(node, "#address-cells", &address_cells.to_be_bytes())?;
+            fdt.set_property(node, "#size-cells", &size_cells.to_be_bytes())?;
+            fdt.set_property(node, "ranges", &[])?;
+            node
+        }
+    };
+
+    let (start, size) = bsp::memory::psci_region();
+    let mut reg = Cells::new();
+    reg.push(start as u64, fdt.property_u32(reserved_memory, "#address-cells").unwrap_or(2))?;
+    reg.push(size as u64, fdt.property_u32(reserved_memory, "#size-cells").unwrap_or(1))?;
+
+    let mut name = NodeName::new();
+    write!(name, "psci@{:x}", start).map_err(|_| "node name too long")?;
+
+    let node = fdt.add_node(reserved_memory, name.as_str())?;
+    fdt.set_property(node, "reg", reg.as_bytes())?;
+    fdt.set_property(node, "no-map", &[])
+}
+
+/// A property value of big-endian cells, e.g. a `reg`
+#[cfg(feature = "psci")]
+struct Cells {
+    buf: [u8; 16],
+    len: usize,
+}
+
+#[cfg(feature = "psci")]
+impl Cells {
+    const fn new() -> Self {
+        Self { buf: [0; 16], len: 0 }
+    }
+
+    /// Append `value` as `cells` cells
+    fn push(&mut self, value: u64, cells: u32) -> Result<(), &'static str> {
+        let bytes = value.to_be_bytes();
+        let bytes = match cells {
+            1 if value <= u64::from(u32::MAX) => &bytes[4..],
+            2 => &bytes[..],
+            _ => return Err("value doesn't fit the #address-cells/#size-cells"),
+        };
+        if self.len + bytes.len() > self.buf.len() {
+            return Err("too many cells");
+        }
+
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+
+        Ok(())
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// A node name built with `write!`, e.g. with a unit address
+#[cfg(feature = "psci")]
+struct NodeName {
+    buf: [u8; 32],
+    len: usize,
+}
+
+#[cfg(feature = "psci")]
+impl NodeName {
+    const fn new() -> Self {
+        Self { buf: [0; 32], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        // only ever written from &strs
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+#[cfg(feature = "psci")]
+impl core::fmt::Write for NodeName {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.len + s.len() > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+
+        self.buf[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+
+        Ok(())
+    }
+}
+
+const fn align4(x: usize) -> usize {
+    (x + 3) & !3
+}
+
+impl Fdt {
+    const fn new() -> Self {
+        Self {
+            buf: [0; CAPACITY],
+            state: Err("not passed by the firmware"),
+        }
+    }
+
+    fn be32(&self, offset: usize) -> u32 {
+        u32::from_be_bytes(self.buf[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn set_be32(&mut self, offset: usize, value: u32) {
+        self.buf[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn header(&self, field: usize) -> usize {
+        self.be32(field) as usize
+    }
+
+    fn set_header(&mut self, field: usize, value: usize) {
+        self.set_be32(field, value as u32);
+    }
+
+    fn total_size(&self) -> usize {
+        self.header(header::TOTALSIZE)
+    }
+
+    /// Copy the device tree at `addr`, laying its blocks out back to back
+    ///
+    /// # Safety
+    ///
+    /// - `addr` must be 0, or point to readable memory
+    unsafe fn load(&mut self, addr: usize) -> Result<(), &'static str> {
+        if addr == 0 {
+            return Err("not passed by the firmware");
+        }
+
+        let src = |offset: usize| (addr + offset) as *const u8;
+        let src_be32 =
+            |offset: usize| u32::from_be(core::ptr::read_unaligned(src(offset) as *const u32));
+
+        if src_be32(header::MAGIC) != FDT_MAGIC {
+            return Err("bad magic");
+        }
+        // version 17 added the structure block's size
+        if src_be32(header::VERSION) < 17 {
+            return Err("unsupported version");
+        }
+
+        // the reservation map ends with an all-zero entry
+        let rsvmap = src_be32(header::OFF_MEM_RSVMAP) as usize;
+        let mut rsvmap_size = 16;
+        while core::ptr::read_unaligned(src(rsvmap + rsvmap_size - 16) as *const [u64; 2]) != [0, 0]
+        {
+            rsvmap_size += 16;
+        }
+        let struct_size = src_be32(header::SIZE_DT_STRUCT) as usize;
+        let strings_size = src_be32(header::SIZE_DT_STRINGS) as usize;
+
+        let struct_start = header::SIZE + rsvmap_size;
+        let strings_start = struct_start + struct_size;
+        let total_size = strings_start + strings_size;
+        if total_size > CAPACITY {
+            return Err("too large");
+        }
+
+        let copy = |from: usize, to: usize, len: usize, buf: &mut [u8]| {
+            core::ptr::copy_nonoverlapping(src(from), buf[to..to + len].as_mut_ptr(), len)
+        };
+        copy(0, 0, header::SIZE, &mut self.buf);
+        copy(rsvmap, header::SIZE, rsvmap_size, &mut self.buf);
+        copy(
+            src_be32(header::OFF_DT_STRUCT) as usize,
+            struct_start,
+            struct_size,
+            &mut self.buf,
+        );
+        copy(
+            src_be32(header::OFF_DT_STRINGS) as usize,
+            strings_start,
+            strings_size,
+            &mut self.buf,
+        );
+
+        self.set_header(header::OFF_MEM_RSVMAP, header::SIZE);
+        self.set_header(header::OFF_DT_STRUCT, struct_start);
+        self.set_header(header::OFF_DT_STRINGS, strings_start);
+        self.set_header(header::TOTALSIZE, total_size);
+
+        Ok(())
+    }
+
+    //----------------------------------------
+    // Editing
+    //----------------------------------------
+
+    /// Make room for `len` bytes at `at`, zeroed. The header's block offsets and sizes are the
+    /// caller's business, except for the total size.
+    fn insert(&mut self, at: usize, len: usize) -> Result<(), &'static str> {
+        let total_size = self.total_size();
+        if total_size + len > CAPACITY {
+            return Err("no room left in the device tree");
+        }
+
+        self.buf.copy_within(at..total_size, at + len);
+        self.buf[at..at + len].fill(0);
+        self.set_header(header::TOTALSIZE, total_size + len);
+
+        Ok(())
+    }
+
+    /// Remove `len` bytes at `at`. The header's block offsets and sizes are the caller's business,
+    /// except for the total size.
+    fn remove(&mut self, at: usize, len: usize) {
+        let total_size = self.total_size();
+
+        self.buf.copy_within(at + len..total_size, at);
+        self.set_header(header::TOTALSIZE, total_size - len);
+    }
+
+    /// Grow (or with a negative `delta`, shrink) the structure block at `at`
+    fn resize_struct(&mut self, at: usize, delta: isize) -> Result<(), &'static str> {
+        if delta > 0 {
+            self.insert(at, delta as usize)?;
+        } else if delta < 0 {
+            self.remove(at, delta.unsigned_abs());
+        }
+
+        let struct_size = self.header(header::SIZE_DT_STRUCT);
+        let strings = self.header(header::OFF_DT_STRINGS);
+        self.set_header(
+            header::SIZE_DT_STRUCT,
+            (struct_size as isize + delta) as usize,
+        );
+        self.set_header(header::OFF_DT_STRINGS, (strings as isize + delta) as usize);
+
+        Ok(())
+    }
+
+    /// Offset of `name` in the strings block, appending it if it isn't there yet
+    fn string_offset(&mut self, name: &str) -> Result<u32, &'static str> {
+        let strings = self.header(header::OFF_DT_STRINGS);
+        let size = self.header(header::SIZE_DT_STRINGS);
+
+        let mut offset = 0;
+        for string in self.buf[strings..strings + size].split(|b| *b == 0) {
+            if string == name.as_bytes() {
+                return Ok(offset as u32);
+            }
+            offset += string.len() + 1;
+        }
+
+        // the strings block is the last one
+        let at = strings + size;
+        self.insert(at, name.len() + 1)?;
+        self.buf[at..at + name.len()].copy_from_slice(name.as_bytes());
+        self.set_header(header::SIZE_DT_STRINGS, size + name.len() + 1);
+
+        Ok(size as u32)
+    }
+
+    /// Set a property of the node at `node`, adding it if it doesn't exist yet. Offsets behind
+    /// the property move.
+    fn set_property(&mut self, node: usize, name: &str, value: &[u8]) -> Result<(), &'static str> {
+        let name_offset = self.string_offset(name)?;
+
+        let prop = match self.find_property(node, name) {
+            Some(prop) => {
+                let old_len = align4(self.be32(prop + 4) as usize);
+                let delta = align4(value.len()) as isize - old_len as isize;
+                self.resize_struct(prop + 12 + old_len.min(align4(value.len())), delta)?;
+                prop
+            }
+            None => {
+                // properties come before the subnodes, right after the name will do
+                let prop = self.skip_name(node);
+                self.resize_struct(prop, (12 + align4(value.len())) as isize)?;
+                self.set_be32(prop, FDT_PROP);
+                prop
+            }
+        };
+
+        self.set_be32(prop + 4, value.len() as u32);
+        self.set_be32(prop + 8, name_offset);
+        self.buf[prop + 12..prop + 12 + value.len()].copy_from_slice(value);
+        self.buf[prop + 12 + value.len()..prop + 12 + align4(value.len())].fill(0);
+
+        Ok(())
+    }
+
+    /// Set a string property of the node at `node`, see [`Fdt::set_property`]
+    fn set_property_str(
+        &mut self,
+        node: usize,
+        name: &str,
+        value: &str,
+    ) -> Result<(), &'static str> {
+        // with the terminating nul
+        let mut buf = [0; 64];
+        if value.len() >= buf.len() {
+            return Err("string property too long");
+        }
+        buf[..value.len()].copy_from_slice(value.as_bytes());
+
+        self.set_property(node, name, &buf[..value.len() + 1])
+    }
+
+    /// Add an empty subnode at the end of the node at `parent`, and return its offset. Offsets
+    /// behind it move.
+    #[cfg(feature = "psci")]
+    fn add_node(&mut self, parent: usize, name: &str) -> Result<usize, &'static str> {
+        // in front of the parent's END_NODE
+        let node = self.skip_node(parent) - 4;
+        let name_len = align4(name.len() + 1);
+
+        self.resize_struct(node, (4 + name_len + 4) as isize)?;
+        self.set_be32(node, FDT_BEGIN_NODE);
+        self.buf[node + 4..node + 4 + name.len()].copy_from_slice(name.as_bytes());
+        self.set_be32(node + 4 + name_len, FDT_END_NODE);
+
+        Ok(node)
+    }
+
+    /// Add a memory reservation entry
+    fn add_mem_reservation(&mut self, addr: u64, size: u64) -> Result<(), &'static str> {
+        // in front of the terminating entry
+        let at = self.header(header::OFF_DT_STRUCT) - 16;
+        self.insert(at, 16)?;
+
+        self.buf[at..at + 8].copy_from_slice(&addr.to_be_bytes());
+        self.buf[at + 8..at + 16].copy_from_slice(&size.to_be_bytes());
+
+        for field in [header::OFF_DT_STRUCT, header::OFF_DT_STRINGS] {
+            let offset = self.header(field);
+            self.set_header(field, offset + 16);
+        }
+
+        Ok(())
+    }
+
+    //----------------------------------------
+    // Walking the structure block
+    //----------------------------------------
+
+    /// Skip NOP tokens
+    fn skip_nops(&self, mut offset: usize) -> usize {
+        while self.be32(offset) == FDT_NOP {
+            offset += 4;
+        }
+        offset
+    }
+
+    /// The name of the node at `node` (its BEGIN_NODE token)
+    fn node_name(&self, node: usize) -> &[u8] {
+        let name = &self.buf[node + 4..];
+        let len = name.iter().position(|b| *b == 0).unwrap_or(0);
+
+        &name[..len]
+    }
+
+    /// Offset of the first token after the node's name
+    fn skip_name(&self, node: usize) -> usize {
+        node + 4 + align4(self.node_name(node).len() + 1)
+    }
+
+    /// Offset of the first token after the property at `prop`
+    fn skip_property(&self, prop: usize) -> usize {
+        prop + 12 + align4(self.be32(prop + 4) as usize)
+    }
+
+    /// Offset of the first token after the node at `node`, subnodes and all
+    fn skip_node(&self, node: usize) -> usize {
+        let mut offset = self.skip_name(node);
+
+        loop {
+            offset = self.skip_nops(offset);
+            match self.be32(offset) {
+                FDT_PROP => offset = self.skip_property(offset),
+                FDT_BEGIN_NODE => offset = self.skip_node(offset),
+                FDT_END_NODE => return offset + 4,
+                // a broken tree, don't walk off into the strings
+                _ => return offset,
+            }
+        }
+    }
+
+    /// The properties of the node at `node` come first, then its subnodes. Returns the offset of
+    /// the first subnode (or of the END_NODE token if there are none).
+    fn skip_properties(&self, node: usize) -> usize {
+        let mut offset = self.skip_nops(self.skip_name(node));
+
+        while self.be32(offset) == FDT_PROP {
+            offset = self.skip_nops(self.skip_property(offset));
+        }
+        offset
+    }
+
+    /// The `index`th subnode of the node at `node`
+    fn child(&self, node: usize, index: usize) -> Option<usize> {
+        let mut offset = self.skip_properties(node);
+
+        for _ in 0..index {
+            if self.be32(offset) != FDT_BEGIN_NODE {
+                return None;
+            }
+            offset = self.skip_nops(self.skip_node(offset));
+        }
+
+        (self.be32(offset) == FDT_BEGIN_NODE).then_some(offset)
+    }
+
+    /// The node at `path`, e.g. "/cpus". A path component without a unit address matches any.
+    fn find_node(&self, path: &str) -> Option<usize> {
+        let mut node = self.skip_nops(self.header(header::OFF_DT_STRUCT));
+
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            node = (0..)
+                .map_while(|index| self.child(node, index))
+                .find(|child| {
+                    let name = self.node_name(*child);
+                    name == component.as_bytes()
+                        || (!component.contains('@')
+                            && name.split(|b| *b == b'@').next() == Some(component.as_bytes()))
+                })?;
+        }
+
+        Some(node)
+    }
+
+    /// The PROP token of the node's property `name`
+    fn find_property(&self, node: usize, name: &str) -> Option<usize> {
+        let strings = self.header(header::OFF_DT_STRINGS);
+        let mut offset = self.skip_nops(self.skip_name(node));
+
+        while self.be32(offset) == FDT_PROP {
+            let name_start = strings + self.be32(offset + 8) as usize;
+            let prop_name = self.buf[name_start..].split(|b| *b == 0).next();
+            if prop_name == Some(name.as_bytes()) {
+                return Some(offset);
+            }
+            offset = self.skip_nops(self.skip_property(offset));
+        }
+
+        None
+    }
+
+    /// The first cell of the node's property `name`
+    fn property_u32(&self, node: usize, name: &str) -> Option<u32> {
+        let prop = self.find_property(node, name)?;
+
+        (self.be32(prop + 4) >= 4).then(|| self.be32(prop + 12))
+    }
+}
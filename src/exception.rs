@@ -0,0 +1,16 @@
+/*
+ * File: exception.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 9:12:40 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Synchronous and asynchronous exception handling.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "_arch/aarch64/exception.rs"]
+mod arch_exception;
+
+pub mod asynchronous;
+
+pub use arch_exception::{handling_init, prepare_handoff};
@@ -0,0 +1,204 @@
+/*
+ * File: asynchronous.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 9:15:02 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Asynchronous exception handling (IRQs).
+//!
+//! The interrupt controller is a driver like any other, the BSP hands it out through
+//! [`irq_manager`]. Drivers register their handlers with it by IRQ number, in
+//! `DeviceDriver::register_and_enable_irq_handler()`.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../_arch/aarch64/exception/asynchronous.rs"]
+mod arch_asynchronous;
+
+use crate::bsp;
+use core::{fmt, marker::PhantomData};
+
+pub use arch_asynchronous::{
+    is_local_irq_masked, local_irq_mask, local_irq_mask_save, local_irq_restore,
+    local_irq_unmask,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Interrupt descriptor
+#[derive(Copy, Clone)]
+pub struct IRQDescriptor {
+    /// Descriptive name, shown by `print_handler()`
+    pub name: &'static str,
+
+    /// Reference to the handler
+    pub handler: &'static (dyn interface::IRQHandler + Sync),
+}
+
+/// The handlers sharing one IRQ number, e.g. the BCM2711's PL011s, which all raise the same
+/// interrupt. Each handler checks its own device, so all of them are called.
+#[derive(Copy, Clone)]
+pub struct IRQHandlers([Option<IRQDescriptor>; MAX_SHARED_HANDLERS]);
+
+/// IRQContext token.
+///
+/// An instance of this type indicates that the local core is currently executing in IRQ
+/// context, aka executing an interrupt vector or subcalls of it.
+///
+/// Concept and implementation derived from the `CriticalSection` introduced in
+/// <https://github.com/rust-embedded/bare-metal>
+#[derive(Clone, Copy)]
+pub struct IRQContext<'irq_context> {
+    _0: PhantomData<&'irq_context ()>,
+}
+
+/// Asynchronous exception handling interfaces
+pub mod interface {
+
+    /// Implemented by types that handle IRQs
+    pub trait IRQHandler {
+        /// Called when the corresponding interrupt is asserted
+        fn handle(&self) -> Result<(), &'static str>;
+    }
+
+    /// IRQ management functions.
+    ///
+    /// The `BSP` is supposed to supply one global instance. Typically implemented by the
+    /// platform's interrupt controller.
+    pub trait IRQManager {
+        /// The IRQ number type depends on the implementation
+        type IRQNumberType: Copy;
+
+        /// Register a handler
+        fn register_handler(
+            &self,
+            irq_number: Self::IRQNumberType,
+            descriptor: super::IRQDescriptor,
+        ) -> Result<(), &'static str>;
+
+        /// Enable an interrupt in the controller
+        fn enable(&self, irq_number: Self::IRQNumberType);
+
+        /// Handle pending interrupts.
+        ///
+        /// This function is called directly from the CPU's IRQ exception vector. On AArch64,
+        /// this means that the respective CPU core has disabled exception handling.
+        /// This function can therefore not be preempted and runs start to finish.
+        ///
+        /// Takes an IRQContext token to ensure it can only be called from IRQ context.
+        #[allow(clippy::trivially_copy_pass_by_ref)]
+        fn handle_pending_irqs<'irq_context>(
+            &'irq_context self,
+            ic: &super::IRQContext<'irq_context>,
+        );
+
+        /// Print list of registered handlers
+        fn print_handler(&self);
+    }
+}
+
+/// How many handlers can share one IRQ number
+const MAX_SHARED_HANDLERS: usize = 2;
+
+/// A wrapper type for a usize with integrated range bound check
+#[derive(Copy, Clone)]
+pub struct BoundedUsize<const MAX_INCLUSIVE: usize>(usize);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl<'irq_context> IRQContext<'irq_context> {
+    /// Creates an IRQContext token.
+    ///
+    /// # Safety
+    ///
+    /// - This must only be called when the current core is in an interrupt context and will not
+    ///   live beyond the end of it. That is, creation is allowed in interrupt vector functions.
+    ///   For example, in the ARMv8-A case, in `extern "C" fn current_elx_irq()`.
+    /// - The lifetime `'irq_context` of the returned instance is unconstrained. User code must
+    ///   not be able to influence the lifetime picked for this type, since that might cause it
+    ///   to be inferred to `'static`.
+    #[inline(always)]
+    pub unsafe fn new() -> Self {
+        IRQContext { _0: PhantomData }
+    }
+}
+
+impl IRQHandlers {
+    /// No handler registered
+    pub const fn new() -> Self {
+        Self([None; MAX_SHARED_HANDLERS])
+    }
+
+    /// Add a handler
+    pub fn add(&mut self, descriptor: IRQDescriptor) -> Result<(), &'static str> {
+        match self.0.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(descriptor);
+                Ok(())
+            }
+            None => Err("Too many handlers sharing the IRQ"),
+        }
+    }
+
+    /// True if no handler was registered
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|slot| slot.is_none())
+    }
+
+    /// Call every handler. Stops at the first failure.
+    pub fn handle(&self) -> Result<(), &'static str> {
+        for descriptor in self.0.iter().flatten() {
+            descriptor.handler.handle()?;
+        }
+
+        Ok(())
+    }
+
+    /// The registered handlers' names
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().flatten().map(|descriptor| descriptor.name)
+    }
+}
+
+impl<const MAX_INCLUSIVE: usize> BoundedUsize<{ MAX_INCLUSIVE }> {
+    /// Creates a new instance if number <= MAX_INCLUSIVE
+    pub const fn new(number: usize) -> Self {
+        assert!(number <= MAX_INCLUSIVE);
+
+        Self(number)
+    }
+
+    /// Return the wrapped number
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl<const MAX_INCLUSIVE: usize> fmt::Display for BoundedUsize<{ MAX_INCLUSIVE }> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Executes the provided closure while IRQs are masked on the executing core.
+///
+/// While the function temporarily changes the HW state of the executing core, it restores it to
+/// the previous state before returning, so this is deemed safe.
+#[inline(always)]
+pub fn exec_with_irq_masked<T>(f: impl FnOnce() -> T) -> T {
+    let saved = local_irq_mask_save();
+    let ret = f();
+    local_irq_restore(saved);
+
+    ret
+}
+
+/// Return a reference to the IRQ manager
+pub fn irq_manager(
+) -> &'static impl interface::IRQManager<IRQNumberType = bsp::exception::asynchronous::IRQNumber> {
+    bsp::exception::asynchronous::irq_manager()
+}
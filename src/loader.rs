@@ -0,0 +1,551 @@
+/*
+ * File: loader.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 2:21:10 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! The loader itself: receive an image from the host, remember it and jump to it.
+//!
+//! Protocol (host side is the pusher):
+//! 1. loader sends three times `3`
+//! 2. host sends the image size, 4 bytes little endian
+//! 3. loader answers `OK`
+//! 4. host sends the image
+//!
+//! An image normally goes to the board's default load address and boots on the executing core. A
+//! [`session`] can instead put images at addresses of their own and start each on a core.
+
+pub mod session;
+
+use crate::{
+    bsp, console, cpu, driver, dtb, exception, memory, memory::mmu::interface::MMU, print, println,
+    status::{self, Phase},
+    synchronization::{interface::Mutex, NullLock},
+    time::Deadline,
+};
+use core::{ops::Range, time::Duration};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Record of the last image received from the host, in front of its copy in the image cache.
+///
+/// The firmware reloads the loader's load window on a reset, so the image is kept in a copy above
+/// the loader (see [`bsp::memory::image_cache_region`]), which boot.s doesn't zero either. The
+/// copy survives a reset as long as RAM is kept, and its CRC is checked before it's trusted.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CachedImageRecord {
+    magic: u64,
+    /// Where the image was received, and goes back to
+    addr: u64,
+    size: u64,
+    crc: u32,
+}
+
+/// While waiting for the host, the request is repeated this often, so a pusher started after the
+/// board still sees it
+const REQUEST_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Once the host has answered, a transfer that stalls for this long is abandoned and the loader
+/// goes back to the handshake. Can be changed at runtime with [`set_idle_timeout`].
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a single read waits before the deadline and the console are checked again
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// When the loader shares the UART with the console, a keypress and the host's answer look the
+/// same. The host sends the 4 size bytes back to back; a single byte followed by this long a gap
+/// is a keypress.
+const KEYPRESS_GAP: Duration = Duration::from_millis(20);
+
+/// The image is read in chunks of this size, each one within the idle timeout
+const CHUNK_SIZE: usize = 256;
+
+static IDLE_TIMEOUT: NullLock<Duration> = NullLock::new(DEFAULT_IDLE_TIMEOUT);
+
+/// How a transfer attempt ended without an image
+enum TransferError {
+    /// Gave up waiting for the host
+    NoAnswer,
+    /// A key was pressed on the console while waiting for the host
+    KeyPressed,
+    /// The transfer started, but failed. Worth another try.
+    Failed(&'static str),
+    /// The image was refused, trying again won't help
+    Refused(&'static str),
+}
+
+/// "LOADCACH"
+const CACHED_IMAGE_MAGIC: u64 = 0x4c4f_4144_4341_4348;
+
+#[link_section = ".image_cache_record"]
+static mut CACHED_IMAGE: CachedImageRecord = CachedImageRecord {
+    magic: 0,
+    addr: 0,
+    size: 0,
+    crc: 0,
+};
+
+/// Fallback image built into the loader, see build.rs. Kept in its own section so link.ld can
+/// check that it still fits.
+#[cfg(feature = "embedded_image")]
+#[link_section = ".embedded_image"]
+static EMBEDDED_IMAGE: [u8; include_bytes!(env!("EMBEDDED_IMAGE_PATH")).len()] =
+    *include_bytes!(env!("EMBEDDED_IMAGE_PATH"));
+
+/// CRC32 (IEEE 802.3) lookup table, built at compile time
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// An image in memory, ready to be booted
+#[derive(Copy, Clone)]
+pub struct Image {
+    pub addr: usize,
+    pub size: usize,
+
+    /// Where execution starts, usually `addr`
+    pub entry: usize,
+}
+
+/// Why waiting for the host ended without an image
+pub enum NoImage {
+    /// The deadline passed without an answer from the host
+    TimedOut,
+    /// A key was pressed on the console
+    KeyPressed,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Image {
+    /// The memory the image occupies, `None` if it would run past the end of the address space
+    pub fn range(&self) -> Option<Range<usize>> {
+        Some(self.addr..self.addr.checked_add(self.size)?)
+    }
+}
+
+/// CRC32 of a memory range
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Request an image from the host and receive it to the board's load address.
+/// Blocks until the host answers, and retries until a transfer succeeds.
+pub fn receive_from_host() -> Image {
+    loop {
+        match try_receive_from_host(None, default_destination()) {
+            Ok(image) => {
+                remember(image);
+                return image;
+            }
+            Err(TransferError::Failed(e) | TransferError::Refused(e)) => report_failure(e),
+            // can't happen without a countdown
+            Err(TransferError::NoAnswer | TransferError::KeyPressed) => {}
+        }
+    }
+}
+
+/// Like [`receive_from_host`], but counts down to `deadline` on the console and gives up when it
+/// passes without an answer from the host, or when a key is pressed.
+pub fn receive_from_host_until(deadline: Deadline) -> Result<Image, NoImage> {
+    match try_receive_from_host(Some(deadline), default_destination()) {
+        Ok(image) => {
+            remember(image);
+            Ok(image)
+        }
+        Err(TransferError::NoAnswer) => Err(NoImage::TimedOut),
+        Err(TransferError::KeyPressed) => Err(NoImage::KeyPressed),
+        // once the host answered, stick with it until a transfer succeeds
+        Err(TransferError::Failed(e) | TransferError::Refused(e)) => {
+            report_failure(e);
+            Ok(receive_from_host())
+        }
+    }
+}
+
+/// Request an image from the host and receive it to `load_addr`, in a single attempt. Once the
+/// size is known, `accept` may still refuse the image, before anything is written. The image
+/// isn't remembered for a later session.
+pub fn receive_from_host_at(
+    load_addr: usize,
+    accept: &dyn Fn(&Image) -> Result<(), &'static str>,
+) -> Result<Image, &'static str> {
+    match try_receive_from_host(None, Destination { load_addr, accept }) {
+        Ok(image) => Ok(image),
+        Err(TransferError::Failed(e) | TransferError::Refused(e)) => Err(e),
+        // can't happen without a countdown
+        Err(TransferError::NoAnswer | TransferError::KeyPressed) => Err("no answer"),
+    }
+}
+
+/// How long a transfer may stall before it is abandoned
+pub fn idle_timeout() -> Duration {
+    IDLE_TIMEOUT.lock(|timeout| *timeout)
+}
+
+/// Change how long a transfer may stall before it is abandoned
+pub fn set_idle_timeout(timeout: Duration) {
+    IDLE_TIMEOUT.lock(|current| *current = timeout);
+}
+
+/// Return the image cached by a previous session, if it is still intact in RAM
+pub fn cached_image() -> Result<Image, &'static str> {
+    let record = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(CACHED_IMAGE)) };
+
+    if record.magic != CACHED_IMAGE_MAGIC {
+        return Err("no image cached");
+    }
+
+    let image = Image {
+        addr: record.addr as usize,
+        size: record.size as usize,
+        entry: record.addr as usize,
+    };
+
+    let cache = bsp::memory::image_cache_region();
+    let loadable = bsp::memory::loadable_range();
+    match image.range() {
+        Some(range) if range.start >= loadable.start && range.end <= loadable.end => {}
+        _ => return Err("cached image record is corrupted"),
+    }
+    if image.size > cache.len() {
+        return Err("cached image record is corrupted");
+    }
+
+    let copy = unsafe { core::slice::from_raw_parts(cache.start as *const u8, image.size) };
+
+    status::set_phase(Phase::Verifying);
+    if crc32(copy) != record.crc {
+        return Err("cached image is corrupted");
+    }
+
+    let kernel = unsafe { core::slice::from_raw_parts_mut(image.addr as *mut u8, image.size) };
+    copy_image(kernel, copy);
+
+    Ok(image)
+}
+
+/// Where the cached image and its record are, as (start, size), so the image being booted can be
+/// kept off them. `None` when nothing is cached.
+pub fn image_cache_in_use() -> Option<(usize, usize)> {
+    let record = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(CACHED_IMAGE)) };
+    if record.magic != CACHED_IMAGE_MAGIC {
+        return None;
+    }
+
+    let start = core::ptr::addr_of!(CACHED_IMAGE) as usize;
+    let end = bsp::memory::image_cache_region().start + record.size as usize;
+    Some((start, end - start))
+}
+
+/// Copy the image built into the loader to the load address
+#[cfg(feature = "embedded_image")]
+pub fn embedded_image() -> Result<Image, &'static str> {
+    let kernel_addr = bsp::memory::board_default_load_address() as *mut u8;
+    let kernel = unsafe { core::slice::from_raw_parts_mut(kernel_addr, EMBEDDED_IMAGE.len()) };
+
+    copy_image(kernel, &EMBEDDED_IMAGE);
+
+    Ok(Image {
+        addr: kernel_addr as usize,
+        size: kernel.len(),
+        entry: kernel_addr as usize,
+    })
+}
+
+/// Without the `embedded_image` feature there is nothing to copy
+#[cfg(not(feature = "embedded_image"))]
+pub fn embedded_image() -> Result<Image, &'static str> {
+    Err("no image built in")
+}
+
+/// Hand control to an image, and start the cores the [`session`] assigned images to. Never
+/// returns.
+pub fn boot(image: Image) -> ! {
+    use console::interface::Write;
+
+    status::set_phase(Phase::Booting);
+
+    session::verify_secondaries();
+    let dtb_addr = dtb::prepare_handoff();
+
+    // last chance to report an overflow, the image may well reuse the loader's memory
+    cpu::stack::check();
+
+    println!("Executing image at {:#x}", image.entry);
+    bsp::console::console().flush();
+    bsp::console::loader_console().flush();
+
+    // nothing may keep interrupting or writing to memory behind the image's back
+    driver::driver_manager().shutdown_all();
+
+    // the secondary cores move from the spin table into the handler, and wait for a CPU_ON there
+    #[cfg(feature = "psci")]
+    unsafe {
+        crate::psci::install(session::runs_own_image)
+    };
+
+    // the image starts the way the firmware would start it: MMU and caches off, everything in RAM
+    unsafe { memory::mmu::mmu().disable_mmu_and_caching() };
+
+    // the image sets up its own exception handling, don't let an IRQ meant for us reach it
+    unsafe { exception::prepare_handoff() };
+
+    // only now, the loader is done with the caches and everything is in RAM
+    unsafe { session::start_secondaries() };
+
+    // at EL1, below the resident handler
+    #[cfg(feature = "psci")]
+    unsafe {
+        crate::psci::boot(image.entry, dtb_addr)
+    };
+
+    // x0 is the device tree, x1-x3 are zero, as the arm64 boot protocol asks
+    #[cfg(not(feature = "psci"))]
+    {
+        let kernel: extern "C" fn(usize, usize, usize, usize) -> ! =
+            unsafe { core::mem::transmute(image.entry) };
+        kernel(dtb_addr, 0, 0, 0);
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Where a received image goes, and whether it may
+struct Destination<'a> {
+    load_addr: usize,
+    accept: &'a dyn Fn(&Image) -> Result<(), &'static str>,
+}
+
+/// The board's default load address, taking any image that fits
+fn default_destination() -> Destination<'static> {
+    Destination {
+        load_addr: bsp::memory::board_default_load_address() as usize,
+        accept: &|_| Ok(()),
+    }
+}
+
+fn report_failure(e: &'static str) {
+    println!("Transfer failed: {}, retrying", e);
+    status::hold(Phase::Failed, Duration::from_secs(2));
+}
+
+/// One transfer attempt to `destination`, see the protocol in the module documentation.
+/// With a `countdown`, gives up waiting for the host once it passes, or on a keypress.
+fn try_receive_from_host(
+    countdown: Option<Deadline>,
+    destination: Destination,
+) -> Result<Image, TransferError> {
+    use console::interface::Write;
+
+    // the protocol may run on a dedicated UART, see bsp::console::loader_console()
+    let link = bsp::console::loader_console();
+    let shares_console = bsp::console::loader_shares_console();
+
+    status::set_phase(Phase::Waiting);
+    println!("Requesting binary!");
+    bsp::console::console().flush();
+
+    link.clear_rx();
+
+    let first = wait_for_host(link, countdown, shares_console)?;
+    let idle_timeout = idle_timeout();
+
+    // Read the rest of the binary's size. During a countdown on the console, a byte on its own
+    // was a keypress.
+    let size_timeout = match countdown {
+        Some(_) if shares_console => KEYPRESS_GAP,
+        _ => idle_timeout,
+    };
+
+    let mut size: u32 = u32::from(first);
+    for shift in [8, 16, 24] {
+        let byte = match read_byte(link, Deadline::after(size_timeout)) {
+            Some(byte) => byte,
+            None if size_timeout == KEYPRESS_GAP => return Err(TransferError::KeyPressed),
+            None => return Err(TransferError::Failed("timed out reading the size")),
+        };
+        size |= u32::from(byte) << shift;
+    }
+
+    let image = Image {
+        addr: destination.load_addr,
+        size: size as usize,
+        entry: destination.load_addr,
+    };
+
+    let loadable = bsp::memory::loadable_range();
+    match image.range() {
+        Some(range) if size != 0 && range.start >= loadable.start && range.end <= loadable.end => {}
+        _ => return Err(TransferError::Failed("invalid image size")),
+    }
+    (destination.accept)(&image).map_err(TransferError::Refused)?;
+
+    link.write_char('O');
+    link.write_char('K');
+
+    status::set_phase(Phase::Receiving);
+    let start_us = bsp::system_timer().counter_us();
+    let kernel = unsafe { core::slice::from_raw_parts_mut(image.addr as *mut u8, image.size) };
+
+    for chunk in kernel.chunks_mut(CHUNK_SIZE) {
+        if link.read_bytes_timeout(chunk, idle_timeout) < chunk.len() {
+            return Err(TransferError::Failed("transfer stalled"));
+        }
+    }
+
+    let elapsed_us = bsp::system_timer().counter_us() - start_us;
+
+    println!(
+        "Received kernel ({} bytes in {} ms, {} KiB/s)",
+        image.size,
+        elapsed_us / 1000,
+        image.size as u64 * 1_000_000 / 1024 / elapsed_us.max(1)
+    );
+    Ok(image)
+}
+
+/// Send three times '3' through UART to notify the pusher to send the kernel / binary, until it
+/// answers with the first byte of the size.
+///
+/// With a `countdown`, the seconds left are shown on the console, and a keypress on the console
+/// (if the loader has its own UART) ends the wait.
+fn wait_for_host(
+    link: &dyn console::interface::All,
+    countdown: Option<Deadline>,
+    shares_console: bool,
+) -> Result<u8, TransferError> {
+    use console::interface::Read;
+
+    let mut shown_secs = None;
+
+    loop {
+        for _ in 0..3 {
+            link.write_char(3 as char);
+        }
+
+        let next_request = Deadline::after(REQUEST_INTERVAL);
+        while !next_request.has_passed() {
+            if let Some(byte) = read_byte(link, Deadline::after(POLL_INTERVAL)) {
+                if shown_secs.is_some() {
+                    println!();
+                }
+                return Ok(byte);
+            }
+
+            let deadline = match countdown {
+                Some(deadline) => deadline,
+                None => continue,
+            };
+
+            if !shares_console && bsp::console::console().try_read_char().is_some() {
+                println!();
+                return Err(TransferError::KeyPressed);
+            }
+
+            if deadline.has_passed() {
+                println!();
+                return Err(TransferError::NoAnswer);
+            }
+
+            // to the nearest second: starts on the full countdown, shows 0 for its last half second
+            let secs = (deadline.remaining().as_millis() + 500) / 1000;
+            if shown_secs != Some(secs) {
+                print!("\rAutoboot in {} s, press any key to stop ", secs);
+                shown_secs = Some(secs);
+            }
+        }
+    }
+}
+
+/// Wait for a byte from the host until `deadline`
+fn read_byte(link: &dyn console::interface::All, deadline: Deadline) -> Option<u8> {
+    loop {
+        if let Some(c) = link.read_char_timeout(POLL_INTERVAL) {
+            return Some(c as u8);
+        }
+
+        if deadline.has_passed() {
+            return None;
+        }
+    }
+}
+
+fn image_bytes(image: &Image) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(image.addr as *const u8, image.size) }
+}
+
+/// Copy an image, the whole words of it with the DMA engine. The core copies the odd tail, and
+/// everything if the DMA engine can't (misaligned, or no free channel).
+fn copy_image(dst: &mut [u8], src: &[u8]) {
+    let words = src.len() & !3;
+    let (dst_words, dst_tail) = dst.split_at_mut(words);
+    let (src_words, src_tail) = src.split_at(words);
+
+    if bsp::dma().memcpy(dst_words, src_words).is_err() {
+        dst_words.copy_from_slice(src_words);
+    }
+    dst_tail.copy_from_slice(src_tail);
+}
+
+/// Copy an image to the image cache, so a later session (after a reset) can boot it again
+fn remember(image: Image) {
+    let cache = bsp::memory::image_cache_region();
+    if image.size > cache.len() {
+        return;
+    }
+
+    status::set_phase(Phase::Verifying);
+
+    // a half written copy must not look valid
+    unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(CACHED_IMAGE.magic), 0) };
+
+    let copy = unsafe { core::slice::from_raw_parts_mut(cache.start as *mut u8, image.size) };
+    copy_image(copy, image_bytes(&image));
+
+    let record = CachedImageRecord {
+        magic: CACHED_IMAGE_MAGIC,
+        addr: image.addr as u64,
+        size: image.size as u64,
+        crc: crc32(copy),
+    };
+
+    unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(CACHED_IMAGE), record) };
+
+    // a reset doesn't write the data cache back
+    memory::clean_dcache_range(cache.start, image.size);
+    memory::clean_dcache_range(
+        core::ptr::addr_of!(CACHED_IMAGE) as usize,
+        core::mem::size_of::<CachedImageRecord>(),
+    );
+}
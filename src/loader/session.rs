@@ -0,0 +1,188 @@
+/*
+ * File: session.rs
+ * Project: RpiOS
+ * File Created: Wednesday, 21st October 2026 9:40:17 am
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Sessions: a different image on each core.
+//!
+//! A session assigns images to cores, each received from the host to a load address of its own,
+//! e.g. Linux on the boot core and a bare-metal control loop on core 3. Assignments must not
+//! overlap. Booting verifies the images of the other cores once more, then [`super::boot`] starts
+//! each on its core, released from the spin table into its entry point at EL2 with x0-x3 zero.
+//! Cores without an image stay parked, for the boot core's image to bring up.
+//!
+//! The device tree handed to the boot core's image keeps it away from the other cores' images:
+//! their cpu nodes are disabled and their memory is reserved.
+
+use super::{crc32, image_bytes, Image};
+use crate::{
+    bsp, cpu, println,
+    synchronization::{interface::Mutex, NullLock},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// An image assigned to a core, and its CRC from when it was received
+#[derive(Copy, Clone)]
+struct Assignment {
+    image: Image,
+    crc: u32,
+}
+
+static ASSIGNMENTS: NullLock<[Option<Assignment>; bsp::cpu::NUM_CORES]> =
+    NullLock::new([None; bsp::cpu::NUM_CORES]);
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Receive an image from the host for `core`, to `load_addr`, starting at `entry` (default: the
+/// load address). Replaces the core's previous image. Refused if it would overlap the image of
+/// another core, if the entry point is outside of it, or if the core never reached the park loop.
+pub fn assign(core: usize, load_addr: usize, entry: Option<usize>) -> Result<Image, &'static str> {
+    if core >= bsp::cpu::NUM_CORES {
+        return Err("no such core");
+    }
+    if core != cpu::core_id() && !cpu::smp::is_parked(core) {
+        return Err("the core isn't waiting for an image");
+    }
+    let entry = entry.unwrap_or(load_addr);
+
+    // about to be overwritten
+    ASSIGNMENTS.lock(|assignments| assignments[core] = None);
+
+    let accept = |image: &Image| {
+        match image.range() {
+            Some(range) if range.contains(&entry) => {}
+            Some(_) => return Err("entry point outside of the image"),
+            None => return Err("image runs past the end of memory"),
+        }
+
+        ASSIGNMENTS.lock(|assignments| {
+            match assignments.iter().flatten().any(|other| overlap(&other.image, image)) {
+                true => Err("overlaps the image of another core"),
+                false => Ok(()),
+            }
+        })
+    };
+
+    let mut image = super::receive_from_host_at(load_addr, &accept)?;
+    image.entry = entry;
+
+    let crc = crc32(image_bytes(&image));
+    ASSIGNMENTS.lock(|assignments| assignments[core] = Some(Assignment { image, crc }));
+
+    Ok(image)
+}
+
+/// Drop all assignments
+pub fn clear() {
+    ASSIGNMENTS.lock(|assignments| *assignments = [None; bsp::cpu::NUM_CORES]);
+}
+
+/// Print the assignments
+pub fn print() {
+    ASSIGNMENTS.lock(|assignments| {
+        for (core, assignment) in assignments.iter().enumerate() {
+            match assignment {
+                Some(Assignment { image, .. }) => println!(
+                    "Core {}: {} bytes at {:#x}, entry {:#x}",
+                    core, image.size, image.addr, image.entry
+                ),
+                None if core == cpu::core_id() => println!("Core {}: loader", core),
+                None if cpu::smp::is_parked(core) => println!("Core {}: parked", core),
+                None => println!("Core {}: not running", core),
+            }
+        }
+    });
+}
+
+/// The image assigned to the executing core, verified
+pub fn boot_core_image() -> Result<Image, &'static str> {
+    let assignment = ASSIGNMENTS
+        .lock(|assignments| assignments[cpu::core_id()])
+        .ok_or("no image assigned to the boot core")?;
+
+    if crc32(image_bytes(&assignment.image)) != assignment.crc {
+        return Err("the boot core's image is corrupted");
+    }
+
+    Ok(assignment.image)
+}
+
+/// The images the other cores start, by core
+pub fn secondary_images() -> [Option<Image>; bsp::cpu::NUM_CORES] {
+    let mut images = [None; bsp::cpu::NUM_CORES];
+
+    ASSIGNMENTS.lock(|assignments| {
+        for (core, assignment) in assignments.iter().enumerate() {
+            if core != cpu::core_id() {
+                images[core] = assignment.map(|assignment| assignment.image);
+            }
+        }
+    });
+
+    images
+}
+
+/// Whether `core` starts an image of its own, instead of waiting for the boot core's image
+#[cfg(feature = "psci")]
+pub fn runs_own_image(core: usize) -> bool {
+    secondary_images()[core].is_some()
+}
+
+//------------------------------------------------------------------------------
+// Loader Interface Code
+//------------------------------------------------------------------------------
+
+/// Verify the images of the other cores right before the handoff. A core whose image has been
+/// overwritten in the meantime stays parked.
+pub(super) fn verify_secondaries() {
+    ASSIGNMENTS.lock(|assignments| {
+        for (core, slot) in assignments.iter_mut().enumerate() {
+            let assignment = match slot {
+                Some(assignment) if core != cpu::core_id() => *assignment,
+                _ => continue,
+            };
+
+            if crc32(image_bytes(&assignment.image)) != assignment.crc {
+                println!("Core {}: image corrupted, the core stays parked", core);
+                *slot = None;
+            } else {
+                println!("Core {}: starting image at {:#x}", core, assignment.image.entry);
+            }
+        }
+    });
+}
+
+/// Release the other cores into their images.
+///
+/// # Safety
+///
+/// - The images are in RAM, the loader no longer needs the cores' memory or the caches.
+pub(super) unsafe fn start_secondaries() {
+    for (core, image) in secondary_images().iter().enumerate() {
+        if let Some(image) = image {
+            let slot = bsp::cpu::spin_table_release_addr(core) as *mut u64;
+            core::ptr::write_volatile(slot, image.entry as u64);
+        }
+    }
+
+    cpu::wake_waiting_cores();
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Whether two images share memory. One running past the end of memory overlaps everything.
+fn overlap(a: &Image, b: &Image) -> bool {
+    match (a.range(), b.range()) {
+        (Some(a), Some(b)) => a.start < b.end && b.start < a.end,
+        _ => true,
+    }
+}
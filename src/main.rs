@@ -2,37 +2,79 @@
 
 //! Enter point of, well, everything
 //! Well, not really, more general metadata, module definitions etc...
+#![feature(alloc_error_handler)]
 #![feature(format_args_nl)]
 #![feature(panic_info_message)]
-#![feature(trait_alias)]
 #![no_main]
 #![no_std]
 
 
+extern crate alloc;
+
 mod cpu;
 mod bsp;
 mod console;
 mod print;
 mod synchronization;
 mod driver;
+mod dtb;
+mod exception;
+mod memory;
 mod panic_handler;
+#[cfg(feature = "psci")]
+mod psci;
+mod boot_mode;
+mod loader;
+mod monitor;
+mod state;
+mod status;
+mod time;
+mod config;
+mod autoboot;
+
+use boot_mode::BootMode;
 
 /// Early init code.
 ///
 /// # Safety
 ///
 /// - Only a single core must be active and running this function.
-unsafe fn kernel_init() -> ! {
-    use crate::driver::interface::DeviceManager;
+/// - `boot_dtb_addr` must be 0, or the address of the device tree the firmware passed.
+unsafe fn kernel_init(boot_dtb_addr: usize) -> ! {
+    use memory::mmu::interface::MMU;
 
-    for i in bsp::driver::driver_manager().all_device_drivers().iter() {
-        if let Err(e) = i.init() {
-            panic!("Error initializing {} driver: {}", i.compatible(), e);
-        }
+    // the MMU is still off, only the canaries catch a stack overflow
+    cpu::stack::init();
+
+    // report faults from here on instead of jumping through whatever VBAR_EL2 holds
+    exception::handling_init();
+
+    // caches on for the loader's own work, loader::boot turns them off again
+    if let Err(e) = memory::mmu::mmu().enable_mmu_and_caching() {
+        panic!("MMU: {}", e);
     }
-    bsp::driver::driver_manager().post_device_driver_init();
 
-    kernel_main();
+    // boot.s released the secondary cores, see which made it
+    cpu::smp::init();
+
+    // the heap's lock needs the MMU
+    memory::heap_alloc::kernel_init_heap_allocator();
+
+    // before anything gets loaded over the firmware's copy
+    dtb::init(boot_dtb_addr);
+
+    if let Err(e) = bsp::driver::register_drivers() {
+        panic!("Error registering drivers: {}", e);
+    }
+    driver::driver_manager().init_drivers();
+    driver::driver_manager().register_irq_handlers();
+
+    // the globals are configured, from here on they are only read
+    state::state_manager().transition_to_single_core_main();
+    exception::asynchronous::local_irq_unmask();
+
+    // the strap pins were sampled as soon as the GPIO driver came up
+    kernel_main(bsp::boot_mode());
 }
 
 const LOADER_LOGO: &str = r#"
@@ -44,43 +86,34 @@ const LOADER_LOGO: &str = r#"
         \/         \/      \/    \/
 "#;
 
-fn kernel_main() -> ! {
-    use bsp::console::console;
-    use console::interface::All;
-
+fn kernel_main(boot_mode: BootMode) -> ! {
     println!("{}", LOADER_LOGO);
     println!("Running on: {}", bsp::board_name());
-    println!();
-    println!("Requesting binary!");
-    console().flush();
-
-    console().clear_rx();
-
-    // send three times '3' through UART to notify the pusher to send the kernel / binary
-    for _ in 0..3 {
-        console().write_char(3 as char);
-    }
-
-    // Read the binary's size.
-    let mut size: u32 = u32::from(console().read_char() as u8);
-    size |= u32::from(console().read_char() as u8) << 8;
-    size |= u32::from(console().read_char() as u8) << 16;
-    size |= u32::from(console().read_char() as u8) << 24;
-
-    console().write_char('O');
-    console().write_char('K');
-
-    let kernel_addr = bsp::memory::board_default_load_address() as *mut u8;
-
-    unsafe {
-        for i in 0..size {
-            core::ptr::write_volatile(kernel_addr.offset(i as isize), console().read_char() as u8);
+    println!("Boot mode:  {}", boot_mode);
+    for core in (0..bsp::cpu::NUM_CORES).filter(|core| *core != cpu::core_id()) {
+        if !cpu::smp::is_parked(core) {
+            println!("Core {} didn't reach the park loop, it stays unused", core);
         }
     }
+    println!();
 
-    println!("Received kernel, executing now!");
-    console().flush();
-
-    let kernel: fn() -> ! = unsafe { core::mem::transmute(kernel_addr) };
-    kernel();
+    match boot_mode {
+        BootMode::Normal => autoboot::run(boot_mode),
+        BootMode::WaitForHost => loader::boot(loader::receive_from_host()),
+        BootMode::BootCached => match loader::cached_image() {
+            Ok(image) => loader::boot(image),
+            Err(e) => {
+                println!("Can't boot the cached image ({}), falling back to the host", e);
+                loader::boot(loader::receive_from_host())
+            }
+        },
+        BootMode::BootEmbedded => match loader::embedded_image() {
+            Ok(image) => loader::boot(image),
+            Err(e) => {
+                println!("Can't boot the embedded image ({}), falling back to the host", e);
+                loader::boot(loader::receive_from_host())
+            }
+        },
+        BootMode::Monitor => monitor::run(boot_mode),
+    }
 }
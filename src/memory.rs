@@ -0,0 +1,18 @@
+/*
+ * File: memory.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:02:15 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Memory management: the MMU, the heap, and keeping RAM and the data cache in sync for the
+//! devices that access RAM behind the core's back (DMA engine, VideoCore mailbox).
+
+#[cfg(target_arch = "aarch64")]
+#[path = "_arch/aarch64/memory.rs"]
+mod arch_memory;
+
+pub mod heap_alloc;
+pub mod mmu;
+
+pub use arch_memory::{clean_dcache_range, clean_invalidate_dcache_range, invalidate_dcache_range};
@@ -0,0 +1,113 @@
+/*
+ * File: heap_alloc.rs
+ * Project: RpiOS
+ * File Created: Wednesday, 21st October 2026 5:47:03 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! The heap, for `alloc`.
+//!
+//! A linked list allocator over the BSP's heap region (see `bsp::memory::heap_region`), which is
+//! above the loader like the rest of it, so an image received from the host never lands on it.
+//! Running out of memory panics, so the panic console reports it.
+//!
+//! The heap is usable from [`kernel_init_heap_allocator`] on, which needs the MMU on: the lock
+//! is a spinlock. Nothing may allocate after `loader::boot` turned the MMU off again.
+
+use crate::{
+    bsp,
+    synchronization::{interface::Mutex, IRQSafeSpinLock},
+};
+use alloc::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use linked_list_allocator::Heap as LinkedListHeap;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A heap allocator that can be lazily initialized.
+pub struct HeapAllocator {
+    inner: IRQSafeSpinLock<LinkedListHeap>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+#[global_allocator]
+static KERNEL_HEAP_ALLOCATOR: HeapAllocator = HeapAllocator::new();
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+#[alloc_error_handler]
+fn alloc_error_handler(layout: Layout) -> ! {
+    panic!(
+        "Out of heap memory: {} bytes (align {}) requested, {} of {} bytes used",
+        layout.size(),
+        layout.align(),
+        KERNEL_HEAP_ALLOCATOR.used(),
+        KERNEL_HEAP_ALLOCATOR.size()
+    );
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the kernel's heap allocator.
+pub fn kernel_heap_allocator() -> &'static HeapAllocator {
+    &KERNEL_HEAP_ALLOCATOR
+}
+
+impl HeapAllocator {
+    /// Create an instance, empty until [`kernel_init_heap_allocator`]
+    pub const fn new() -> Self {
+        Self {
+            inner: IRQSafeSpinLock::new(LinkedListHeap::empty()),
+        }
+    }
+
+    /// Bytes allocated
+    pub fn used(&self) -> usize {
+        self.inner.lock(|heap| heap.used())
+    }
+
+    /// Size of the heap in bytes
+    pub fn size(&self) -> usize {
+        self.inner.lock(|heap| heap.size())
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+unsafe impl GlobalAlloc for HeapAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // null makes the caller call the alloc error handler
+        self.inner
+            .lock(|heap| heap.allocate_first_fit(layout))
+            .map_or(ptr::null_mut(), |allocation| allocation.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner
+            .lock(|heap| heap.deallocate(ptr::NonNull::new_unchecked(ptr), layout));
+    }
+}
+
+/// Hand the BSP's heap region to the allocator.
+///
+/// # Safety
+///
+/// - Only once, with the MMU on.
+pub unsafe fn kernel_init_heap_allocator() {
+    let region = bsp::memory::heap_region();
+
+    KERNEL_HEAP_ALLOCATOR
+        .inner
+        .lock(|heap| heap.init(region.start as *mut u8, region.len()));
+}
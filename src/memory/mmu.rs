@@ -0,0 +1,203 @@
+/*
+ * File: mmu.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:11:37 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Memory Management Unit.
+//!
+//! The loader only uses the MMU to turn the caches on: everything is identity mapped, RAM as
+//! cacheable normal memory, the peripherals as device memory. The BSP describes which is which
+//! (see `bsp::memory::mmu`), the arch code builds the translation tables from that.
+//!
+//! Before an image is started, the caches are cleaned and the MMU turned off again, so it finds
+//! the board as the firmware left it.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../_arch/aarch64/memory/mmu.rs"]
+mod arch_mmu;
+
+mod translation_table;
+
+use core::{fmt, ops::RangeInclusive};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+pub use arch_mmu::mmu;
+
+/// MMU enable errors
+pub enum MMUEnableError {
+    AlreadyEnabled,
+    Other(&'static str),
+}
+
+/// Memory Management interfaces
+pub mod interface {
+    use super::*;
+
+    /// MMU functions
+    pub trait MMU {
+        /// Build the translation tables, then turn the MMU and the caches on.
+        ///
+        /// # Safety
+        ///
+        /// - Changes the hardware's global state.
+        unsafe fn enable_mmu_and_caching(&self) -> Result<(), MMUEnableError>;
+
+        /// Returns true if the MMU is enabled
+        fn is_enabled(&self) -> bool;
+
+        /// Write everything cached back to RAM, then turn the MMU and the caches off.
+        ///
+        /// # Safety
+        ///
+        /// - Changes the hardware's global state.
+        unsafe fn disable_mmu_and_caching(&self);
+    }
+}
+
+/// Describes the characteristics of a translation granule
+pub struct TranslationGranule<const GRANULE_SIZE: usize>;
+
+/// Describes the size of an address space
+pub struct AddressSpace<const AS_SIZE: usize>;
+
+/// Memory attributes of a range
+#[derive(Copy, Clone)]
+pub enum MemAttributes {
+    CacheableDRAM,
+    Device,
+}
+
+/// Access permissions of a range
+#[derive(Copy, Clone)]
+pub enum AccessPermissions {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Collection of memory attributes
+#[derive(Copy, Clone)]
+pub struct AttributeFields {
+    pub mem_attributes: MemAttributes,
+    pub acc_perms: AccessPermissions,
+    pub execute_never: bool,
+}
+
+/// A range of the address space that isn't plain RAM
+pub struct TranslationDescriptor {
+    pub name: &'static str,
+    pub range: RangeInclusive<usize>,
+    pub attribute_fields: AttributeFields,
+}
+
+/// The BSP's memory map: the special ranges, everything else is RAM
+pub struct KernelVirtualLayout<const NUM_SPECIAL_RANGES: usize> {
+    /// The last (inclusive) address of the address space
+    max_virt_addr_inclusive: usize,
+
+    /// Array of descriptors for non-standard (normal cacheable DRAM) memory regions
+    inner: [TranslationDescriptor; NUM_SPECIAL_RANGES],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl fmt::Display for MMUEnableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MMUEnableError::AlreadyEnabled => write!(f, "MMU is already enabled"),
+            MMUEnableError::Other(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl<const GRANULE_SIZE: usize> TranslationGranule<GRANULE_SIZE> {
+    /// The granule's size
+    pub const SIZE: usize = Self::size_checked();
+
+    /// The granule's shift, aka log2(size)
+    pub const SHIFT: usize = Self::SIZE.trailing_zeros() as usize;
+
+    const fn size_checked() -> usize {
+        assert!(GRANULE_SIZE.is_power_of_two());
+
+        GRANULE_SIZE
+    }
+}
+
+impl<const AS_SIZE: usize> AddressSpace<AS_SIZE> {
+    /// The address space size
+    pub const SIZE: usize = Self::size_checked();
+
+    /// The address space shift, aka log2(size)
+    pub const SIZE_SHIFT: usize = Self::SIZE.trailing_zeros() as usize;
+
+    const fn size_checked() -> usize {
+        assert!(AS_SIZE.is_power_of_two());
+
+        // Check for architectural restrictions as well.
+        Self::arch_address_space_size_sanity_checks();
+
+        AS_SIZE
+    }
+}
+
+impl Default for AttributeFields {
+    fn default() -> AttributeFields {
+        AttributeFields {
+            mem_attributes: MemAttributes::CacheableDRAM,
+            acc_perms: AccessPermissions::ReadWrite,
+            // the loader runs from RAM
+            execute_never: false,
+        }
+    }
+}
+
+impl<const NUM_SPECIAL_RANGES: usize> KernelVirtualLayout<{ NUM_SPECIAL_RANGES }> {
+    /// Create a new instance
+    pub const fn new(max: usize, layout: [TranslationDescriptor; NUM_SPECIAL_RANGES]) -> Self {
+        Self {
+            max_virt_addr_inclusive: max,
+            inner: layout,
+        }
+    }
+
+    /// The attributes of the range holding `virt_addr`. Identity mapped, so the output address is
+    /// `virt_addr` itself.
+    pub fn virt_addr_properties(
+        &self,
+        virt_addr: usize,
+    ) -> Result<(usize, AttributeFields), &'static str> {
+        if virt_addr > self.max_virt_addr_inclusive {
+            return Err("Address out of range");
+        }
+
+        for i in self.inner.iter() {
+            if i.range.contains(&virt_addr) {
+                return Ok((virt_addr, i.attribute_fields));
+            }
+        }
+
+        Ok((virt_addr, AttributeFields::default()))
+    }
+
+    /// Print the memory layout
+    pub fn print_layout(&self) {
+        use crate::println;
+
+        for i in self.inner.iter() {
+            println!(
+                "      {:#010x} - {:#010x} | {}",
+                i.range.start(),
+                i.range.end(),
+                i.name
+            );
+        }
+        println!("      everything else        | RAM, cached (stack guard pages unmapped)");
+    }
+}
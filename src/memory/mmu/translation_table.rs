@@ -0,0 +1,18 @@
+/*
+ * File: translation_table.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 8:13:20 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Translation table.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "../../_arch/aarch64/memory/mmu/translation_table.rs"]
+mod arch_translation_table;
+
+//--------------------------------------------------------------------------------------------------
+// Architectural Public Reexports
+//--------------------------------------------------------------------------------------------------
+
+pub use arch_translation_table::KernelTranslationTable;
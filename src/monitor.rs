@@ -0,0 +1,327 @@
+/*
+ * File: monitor.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 3:02:55 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Interactive monitor on the console.
+//!
+//! Entered with the `monitor` boot mode. Lets a human look around and decide where the image
+//! comes from.
+
+use crate::{
+    boot_mode::BootMode, bsp, console, cpu, dtb, exception, loader, memory, print, println, time,
+};
+use core::time::Duration;
+
+/// Longest command line accepted
+const MAX_LINE: usize = 64;
+
+/// Owner of the GPIO pins changed from the monitor
+const GPIO_OWNER: &str = "monitor";
+
+/// GPIO settings by name, as the `gpio` command takes and shows them
+const PIN_FUNCTIONS: [(&str, bsp::gpio::PinFunction); 8] = [
+    ("in", bsp::gpio::PinFunction::Input),
+    ("out", bsp::gpio::PinFunction::Output),
+    ("alt0", bsp::gpio::PinFunction::Alt0),
+    ("alt1", bsp::gpio::PinFunction::Alt1),
+    ("alt2", bsp::gpio::PinFunction::Alt2),
+    ("alt3", bsp::gpio::PinFunction::Alt3),
+    ("alt4", bsp::gpio::PinFunction::Alt4),
+    ("alt5", bsp::gpio::PinFunction::Alt5),
+];
+
+const PULLS: [(&str, bsp::gpio::Pull); 3] = [
+    ("off", bsp::gpio::Pull::Off),
+    ("down", bsp::gpio::Pull::Down),
+    ("up", bsp::gpio::Pull::Up),
+];
+
+const EVENTS: [(&str, bsp::gpio::Event); 6] = [
+    ("rising", bsp::gpio::Event::RisingEdge),
+    ("falling", bsp::gpio::Event::FallingEdge),
+    ("high", bsp::gpio::Event::HighLevel),
+    ("low", bsp::gpio::Event::LowLevel),
+    ("async-rising", bsp::gpio::Event::AsyncRisingEdge),
+    ("async-falling", bsp::gpio::Event::AsyncFallingEdge),
+];
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Run the monitor prompt. Only returns control by booting an image.
+pub fn run(boot_mode: BootMode) -> ! {
+    println!("Entering monitor, type 'help' for the list of commands");
+
+    let mut line = [0u8; MAX_LINE];
+    loop {
+        print!("> ");
+        let len = read_line(&mut line);
+        let text = core::str::from_utf8(&line[..len]).unwrap_or("").trim();
+        let (command, argument) = text.split_once(' ').unwrap_or((text, ""));
+
+        match command {
+            "" => {}
+            "help" => help(),
+            "info" => info(boot_mode),
+            "idle" => idle(argument.trim()),
+            "irqs" => irqs(),
+            "gpio" => gpio(argument.trim()),
+            "load" => loader::boot(loader::receive_from_host()),
+            "boot" => match loader::cached_image() {
+                Ok(image) => loader::boot(image),
+                Err(e) => println!("Can't boot the cached image: {}", e),
+            },
+            "embedded" => match loader::embedded_image() {
+                Ok(image) => loader::boot(image),
+                Err(e) => println!("Can't boot the embedded image: {}", e),
+            },
+            "assign" => assign(argument.trim()),
+            "session" => session(argument.trim()),
+            "start" => match loader::session::boot_core_image() {
+                Ok(image) => loader::boot(image),
+                Err(e) => println!("Can't start the session: {}", e),
+            },
+            _ => println!("Unknown command '{}', type 'help' for the list of commands", command),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Code
+//--------------------------------------------------------------------------------------------------
+
+/// Read a line from the console with echo and backspace. Returns its length.
+fn read_line(buf: &mut [u8]) -> usize {
+    use console::interface::{Read, Write};
+
+    let console = bsp::console::console();
+    let mut len = 0;
+
+    loop {
+        match console.read_char() {
+            '\r' | '\n' => {
+                println!();
+                return len;
+            }
+            // backspace / delete: erase the last character on the terminal too
+            '\x08' | '\x7f' => {
+                if len > 0 {
+                    len -= 1;
+                    print!("\x08 \x08");
+                }
+            }
+            c if (c.is_ascii_graphic() || c == ' ') && len < buf.len() => {
+                buf[len] = c as u8;
+                len += 1;
+                console.write_char(c);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn help() {
+    println!("help     - this text");
+    println!("info     - board, boot mode, uptime and cached image");
+    println!("load     - request an image from the host and boot it");
+    println!("boot     - boot the image cached by a previous session");
+    println!("embedded - boot the image built into the loader");
+    println!("assign   - 'assign <core> <load address> [entry]': request an image for a core");
+    println!("session  - show the images assigned to cores, 'session clear' drops them");
+    println!("start    - start every core with an image, the boot core last");
+    println!("idle     - show, or with an argument in ms set, how long a transfer may stall");
+    println!("irqs     - IRQ mask state and registered IRQ handlers");
+    println!("gpio     - 'gpio <pin> [setting]': show a pin, or claim it and change a setting:");
+    println!("           'func in|out|alt0-alt5', 'pull off|down|up', 'set 0|1',");
+    println!("           'detect <event>' or 'ignore <event>' for rising, falling, high, low,");
+    println!("           async-rising and async-falling, 'clear' the event status, or 'release'");
+}
+
+fn idle(argument: &str) {
+    if !argument.is_empty() {
+        match argument.parse::<u64>() {
+            Ok(ms) if ms > 0 => loader::set_idle_timeout(Duration::from_millis(ms)),
+            _ => {
+                println!("Expected a timeout in ms, e.g. 'idle 500'");
+                return;
+            }
+        }
+    }
+
+    println!("Idle timeout: {} ms", loader::idle_timeout().as_millis());
+}
+
+fn assign(argument: &str) {
+    let mut words = argument.split_whitespace();
+    let core = words.next().and_then(parse_number);
+    let load_addr = words.next().and_then(parse_number);
+    let entry = words.next().map(parse_number);
+
+    let (core, load_addr, entry) = match (core, load_addr, entry, words.next()) {
+        (Some(core), Some(load_addr), None, None) => (core, load_addr, None),
+        (Some(core), Some(load_addr), Some(Some(entry)), None) => (core, load_addr, Some(entry)),
+        _ => {
+            println!("Expected a core, a load address and an optional entry point,");
+            println!("e.g. 'assign 3 0x1000000'");
+            return;
+        }
+    };
+
+    match loader::session::assign(core, load_addr, entry) {
+        Ok(image) => println!("Core {}: {} bytes at {:#x}", core, image.size, image.addr),
+        Err(e) => println!("Can't assign the image: {}", e),
+    }
+}
+
+fn session(argument: &str) {
+    match argument {
+        "" => loader::session::print(),
+        "clear" => loader::session::clear(),
+        _ => println!("Expected nothing or 'clear'"),
+    }
+}
+
+/// A decimal number, or a hex one with a `0x` prefix
+fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn gpio(argument: &str) {
+    let mut words = argument.split_whitespace();
+    let pin = match words.next().and_then(parse_number) {
+        Some(pin) => pin as u32,
+        None => {
+            println!("Expected a pin and an optional setting, e.g. 'gpio 21 pull down'");
+            return;
+        }
+    };
+
+    let result = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => Ok(()),
+        (Some(setting), value, None) => change_pin(pin, setting, value.unwrap_or("")),
+        _ => Err("too many arguments"),
+    };
+
+    if let Err(e) = result {
+        println!("Can't change GPIO {}: {}", pin, e);
+        return;
+    }
+
+    show_pin(pin);
+}
+
+/// Claim a pin for the monitor and change one of its settings
+fn change_pin(pin: u32, setting: &str, value: &str) -> Result<(), &'static str> {
+    fn lookup<T: Copy>(table: &[(&str, T)], name: &str) -> Result<T, &'static str> {
+        table
+            .iter()
+            .find(|(entry, _)| *entry == name)
+            .map(|(_, item)| *item)
+            .ok_or("unknown value, see 'help'")
+    }
+
+    let gpio = bsp::gpio::driver();
+
+    if setting == "release" {
+        return gpio.release(pin, GPIO_OWNER);
+    }
+    gpio.claim(pin, GPIO_OWNER)?;
+
+    match setting {
+        "func" => match lookup(&PIN_FUNCTIONS, value)? {
+            // start driving the level the pin is at, rather than whatever GPSET/GPCLR last left
+            bsp::gpio::PinFunction::Output => {
+                gpio.init_output_pin(pin, gpio.read_level(pin)?, GPIO_OWNER)
+            }
+            function => gpio.set_function(pin, function, GPIO_OWNER),
+        },
+        "pull" => gpio.set_pull(&[pin], lookup(&PULLS, value)?, GPIO_OWNER),
+        "set" => gpio.set_level(pin, lookup(&[("0", false), ("1", true)], value)?, GPIO_OWNER),
+        "detect" => gpio.set_event_detect(pin, lookup(&EVENTS, value)?, true, GPIO_OWNER),
+        "ignore" => gpio.set_event_detect(pin, lookup(&EVENTS, value)?, false, GPIO_OWNER),
+        "clear" => gpio.clear_event(pin, GPIO_OWNER),
+        _ => Err("unknown setting, see 'help'"),
+    }
+}
+
+fn show_pin(pin: u32) {
+    let gpio = bsp::gpio::driver();
+
+    let (function, level, owner, event) = match (
+        gpio.function(pin),
+        gpio.read_level(pin),
+        gpio.owner(pin),
+        gpio.event_detected(pin),
+    ) {
+        (Ok(function), Ok(level), Ok(owner), Ok(event)) => (function, level, owner, event),
+        _ => {
+            println!("No GPIO {}", pin);
+            return;
+        }
+    };
+
+    let function = PIN_FUNCTIONS
+        .iter()
+        .find(|(_, entry)| *entry == function)
+        .map_or("?", |(name, _)| *name);
+
+    println!(
+        "GPIO {}: {}, {}, {}, {}",
+        pin,
+        function,
+        if level { "high" } else { "low" },
+        owner.unwrap_or("not claimed"),
+        if event { "event detected" } else { "no event" }
+    );
+}
+
+fn irqs() {
+    use exception::asynchronous::interface::IRQManager;
+
+    let state = if exception::asynchronous::is_local_irq_masked() { "masked" } else { "unmasked" };
+    println!("IRQs:      {}", state);
+    println!("Handlers:");
+    exception::asynchronous::irq_manager().print_handler();
+}
+
+fn info(boot_mode: BootMode) {
+    use memory::mmu::interface::MMU;
+    use time::interface::TimeManager;
+
+    println!("Board:     {}", bsp::board_name());
+    println!("Core:      {}", cpu::core_id());
+    println!("Boot mode: {}", boot_mode);
+    println!("Uptime:    {:?}", time::time_manager().uptime());
+    println!("Timer:     {} ns resolution", time::time_manager().resolution().as_nanos());
+    println!(
+        "Overruns:  {} bytes lost on the loader link",
+        bsp::console::loader_console().rx_overruns()
+    );
+
+    match loader::cached_image() {
+        Ok(image) => println!("Cached:    {} bytes at {:#x}", image.size, image.addr),
+        Err(e) => println!("Cached:    {}", e),
+    }
+
+    let heap = memory::heap_alloc::kernel_heap_allocator();
+    println!("Heap:      {} of {} bytes used", heap.used(), heap.size());
+
+    match dtb::info() {
+        Ok((addr, size)) => println!("DTB:       {} bytes at {:#x}", size, addr),
+        Err(e) => println!("DTB:       none, {}", e),
+    }
+
+    if memory::mmu::mmu().is_enabled() {
+        println!("MMU:       on, identity mapped:");
+        bsp::memory::mmu::virt_mem_layout().print_layout();
+    } else {
+        println!("MMU:       off");
+    }
+}
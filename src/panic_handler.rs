@@ -1,5 +1,5 @@
 use core::{fmt,panic::PanicInfo};
-use crate::{cpu, bsp};
+use crate::{bsp, status};
 
 fn _panic_print(args: fmt::Arguments) {
     use fmt::Write;
@@ -24,5 +24,5 @@ fn panic(_info: &PanicInfo) -> ! {
     else {
         panic_println!("\nKernel panic!")
     }
-    cpu::wait_forever()
+    status::panic_blink()
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+/*
+ * File: psci.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 10:58:12 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Resident PSCI handler.
+//!
+//! With the `psci_hvc` or `psci_smc` feature, the loader doesn't start the image at EL2. It leaves
+//! a small Power State Coordination Interface (PSCI 0.2) handler resident at EL2 and starts the
+//! image at EL1, from where the image calls the handler through HVC or SMC to bring the secondary
+//! cores up and down (CPU_ON, CPU_OFF, AFFINITY_INFO), and to power off or reset the board
+//! (SYSTEM_OFF, SYSTEM_RESET, through the BCM watchdog).
+//!
+//! The handler's code, vector table and state live in pages of their own (see link.ld), which
+//! the device tree handed to the image reserves, next to the `/psci` node saying how to call it.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "_arch/aarch64/psci.rs"]
+mod arch_psci;
+
+pub use arch_psci::{boot, install};
+
+/// How the image calls the handler. Only the conduit the `psci_*` feature picks is built.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Conduit {
+    #[cfg(not(feature = "psci_smc"))]
+    Hvc,
+    #[cfg(feature = "psci_smc")]
+    Smc,
+}
+
+/// The conduit, picked by the `psci_*` feature
+#[cfg(not(feature = "psci_smc"))]
+pub const CONDUIT: Conduit = Conduit::Hvc;
+#[cfg(feature = "psci_smc")]
+pub const CONDUIT: Conduit = Conduit::Smc;
+
+impl Conduit {
+    /// The `method` of the device tree's `/psci` node
+    pub fn method(&self) -> &'static str {
+        match self {
+            #[cfg(not(feature = "psci_smc"))]
+            Conduit::Hvc => "hvc",
+            #[cfg(feature = "psci_smc")]
+            Conduit::Smc => "smc",
+        }
+    }
+}
@@ -0,0 +1,81 @@
+/*
+ * File: state.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 9:48:10 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! The loader's own state: still initializing, or running.
+//!
+//! Init is where globals get configured (see [`crate::synchronization::InitStateLock`]): a single
+//! core runs, with IRQs masked.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Different stages in the loader's execution
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    /// The loader starts in this state: a single core configures the globals
+    Init,
+
+    /// Init is done, still on a single core
+    SingleCoreMain,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Maintains the loader state and state transitions
+pub struct StateManager(AtomicU8);
+
+//--------------------------------------------------------------------------------------------------
+// Global instances
+//--------------------------------------------------------------------------------------------------
+
+static STATE_MANAGER: StateManager = StateManager::new();
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Return a reference to the global StateManager
+pub fn state_manager() -> &'static StateManager {
+    &STATE_MANAGER
+}
+
+impl StateManager {
+    const INIT: u8 = 0;
+    const SINGLE_CORE_MAIN: u8 = 1;
+
+    /// Create an instance
+    pub const fn new() -> Self {
+        Self(AtomicU8::new(Self::INIT))
+    }
+
+    fn state(&self) -> State {
+        match self.0.load(Ordering::Acquire) {
+            Self::INIT => State::Init,
+            Self::SINGLE_CORE_MAIN => State::SingleCoreMain,
+            _ => panic!("Invalid loader state"),
+        }
+    }
+
+    /// Return true while the loader is initializing
+    pub fn is_init(&self) -> bool {
+        self.state() == State::Init
+    }
+
+    /// Init is done. Only the boot core changes the state, so a load and a store do.
+    pub fn transition_to_single_core_main(&self) {
+        if self.state() != State::Init {
+            panic!("transition_to_single_core_main() called outside of init");
+        }
+
+        self.0.store(Self::SINGLE_CORE_MAIN, Ordering::Release);
+    }
+}
@@ -0,0 +1,188 @@
+/*
+ * File: status.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 6:15:32 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Boot progress indication on a status LED.
+//!
+//! Each loader phase blinks its own pattern, so a board without a serial cable still tells what
+//! it is doing. The BSP's step timer interrupts when a step is over and the pattern moves on
+//! ([`next_step`]), whatever the loader is busy with meanwhile.
+
+use crate::{
+    bsp, exception,
+    synchronization::interface::Mutex,
+    synchronization::NullLock,
+    time::{self, interface::TimeManager},
+};
+use core::time::Duration;
+
+/// Status LED interface, implemented by the BSP
+pub mod interface {
+    /// A LED that can be switched on and off
+    pub trait Led {
+        /// Switch the LED on or off
+        fn set(&self, on: bool);
+    }
+}
+
+/// Loader phases, each with its own blink pattern
+#[derive(Copy, Clone, PartialEq)]
+pub enum Phase {
+    /// Waiting for the host: slow heartbeat
+    Waiting,
+    /// Receiving an image: fast blink
+    Receiving,
+    /// Verifying an image: double blink
+    Verifying,
+    /// About to jump to the image: solid on
+    Booting,
+    /// Something went wrong (the loader retries): triple blink
+    Failed,
+    /// Panic: SOS, forever
+    Panic,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Private Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// One step of a blink pattern: LED state and how long to keep it, in ms
+struct Step(bool, u64);
+
+/// Patterns repeat until the phase changes
+fn pattern(phase: Phase) -> &'static [Step] {
+    const SHORT: u64 = 150;
+    const LONG: u64 = 450;
+
+    match phase {
+        Phase::Waiting => &[Step(true, 100), Step(false, 900)],
+        Phase::Receiving => &[Step(true, 50), Step(false, 50)],
+        Phase::Verifying => &[
+            Step(true, 100),
+            Step(false, 100),
+            Step(true, 100),
+            Step(false, 700),
+        ],
+        Phase::Booting => &[Step(true, u64::MAX)],
+        Phase::Failed => &[
+            Step(true, 200),
+            Step(false, 200),
+            Step(true, 200),
+            Step(false, 200),
+            Step(true, 200),
+            Step(false, 1000),
+        ],
+        Phase::Panic => &[
+            Step(true, SHORT),
+            Step(false, SHORT),
+            Step(true, SHORT),
+            Step(false, SHORT),
+            Step(true, SHORT),
+            Step(false, SHORT),
+            Step(true, LONG),
+            Step(false, SHORT),
+            Step(true, LONG),
+            Step(false, SHORT),
+            Step(true, LONG),
+            Step(false, SHORT),
+            Step(true, SHORT),
+            Step(false, SHORT),
+            Step(true, SHORT),
+            Step(false, SHORT),
+            Step(true, SHORT),
+            Step(false, 3 * LONG),
+        ],
+    }
+}
+
+struct IndicatorInner {
+    phase: Option<Phase>,
+    step: usize,
+}
+
+// Shared with the step timer's IRQ handler, only taken with IRQs masked elsewhere.
+static INDICATOR: NullLock<IndicatorInner> = NullLock::new(IndicatorInner { phase: None, step: 0 });
+
+impl IndicatorInner {
+    /// Apply the current step to the LED and start the step timer, returning how long the step
+    /// lasts. `None` for a step that lasts until the phase changes.
+    fn apply_step(&mut self, phase: Phase) -> Option<Duration> {
+        use interface::Led;
+
+        let Step(on, duration_ms) = pattern(phase)[self.step];
+
+        bsp::status::status_led().set(on);
+        match duration_ms {
+            u64::MAX => None,
+            ms => {
+                let duration = Duration::from_millis(ms);
+                bsp::status::start_step_timer(duration);
+                Some(duration)
+            }
+        }
+    }
+
+    /// Move the pattern on to its next step, see [`IndicatorInner::apply_step`]
+    fn next_step(&mut self) -> Option<Duration> {
+        let phase = self.phase?;
+
+        self.step = (self.step + 1) % pattern(phase).len();
+        self.apply_step(phase)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+/// Switch to the pattern of a new phase. Setting the current phase again does nothing.
+pub fn set_phase(phase: Phase) {
+    exception::asynchronous::exec_with_irq_masked(|| {
+        INDICATOR.lock(|inner| {
+            if inner.phase == Some(phase) {
+                return;
+            }
+
+            inner.phase = Some(phase);
+            inner.step = 0;
+            inner.apply_step(phase);
+        })
+    });
+}
+
+/// Move the current pattern on to its next step. Called by the BSP's step timer IRQ handler once
+/// a step is over.
+pub fn next_step() {
+    INDICATOR.lock(|inner| inner.next_step());
+}
+
+/// Show a phase for `duration` before returning
+pub fn hold(phase: Phase, duration: Duration) {
+    set_phase(phase);
+    time::time_manager().spin_for(duration);
+}
+
+/// Blink the panic pattern forever.
+///
+/// The panic may come from IRQ context, or from the interrupt controller itself, so the steps are
+/// timed here instead of by the step timer's IRQ.
+pub fn panic_blink() -> ! {
+    exception::asynchronous::local_irq_mask();
+
+    let mut step = INDICATOR.lock(|inner| {
+        inner.phase = Some(Phase::Panic);
+        inner.step = 0;
+        inner.apply_step(Phase::Panic)
+    });
+
+    loop {
+        // none of the panic pattern's steps lasts forever
+        if let Some(duration) = step {
+            time::time_manager().spin_for(duration);
+        }
+        step = INDICATOR.lock(|inner| inner.next_step());
+    }
+}
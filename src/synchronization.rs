@@ -5,8 +5,20 @@
  * Author: Elad Matia (elad.matia@gmail.com)
  */
 
+use crate::{exception, state};
 use core::cell::UnsafeCell;
 
+// Only the PL011 driver buffers what it receives.
+#[cfg(pl011)]
+mod ring_buffer;
+
+#[cfg(pl011)]
+pub use ring_buffer::RingBuffer;
+
+mod spin_lock;
+
+pub use spin_lock::IRQSafeSpinLock;
+
 pub mod interface {
     /// Any object wraps with this mutex trait guarantees exclusive access,
     /// ensures no data races.
@@ -20,6 +32,20 @@ pub mod interface {
         where
             F: FnOnce(&mut Self::Data) -> R;
     }
+
+    /// A reader-writer exclusion type: any number of readers, or a single writer, at a time
+    pub trait ReadWriteEx {
+        /// The data type wrapped with the lock
+        type Data;
+
+        /// Grants the closure exclusive `mutable` access to the data within. The reference can't
+        /// outlive the call, or it would still be around once the data is shared.
+        fn write<R>(&self, func: impl FnOnce(&mut Self::Data) -> R) -> R;
+
+        /// Grants the closure shared access to the data within. Like for `write`, the reference
+        /// can't outlive the call.
+        fn read<R>(&self, func: impl FnOnce(&Self::Data) -> R) -> R;
+    }
 }
 
 /// Dummy lock that doesn't really do anything (i.e doesn't support multithreaded operations for instance).
@@ -33,6 +59,7 @@ pub mod interface {
 /// Wrapping it in a lock may help (core:cell:Cell?). In the case of my RpiOS, the lock function doesn't really do anything beside
 /// calling get on the object in an unsafe block. In the future, where multithreaded will be used, the lock function will probably use
 /// atomic operations or whatever, to ensure exclusive access to the data.
+/// That real lock is [`IRQSafeSpinLock`]: same interface, so switching a global over is a change of its type.
 
 pub struct NullLock<T>
 where
@@ -68,3 +95,51 @@ impl<T> interface::Mutex for NullLock<T> {
         func(data)
     }
 }
+
+/// A pseudo-lock for globals configured at runtime: writable during init, read-only afterwards.
+///
+/// Init runs on a single core with IRQs masked (see [`state`]), so a write can't race with
+/// anything. Afterwards nothing writes, so reads need no locking at all and are as cheap as
+/// reading a plain `static`. A write after init panics.
+pub struct InitStateLock<T>
+where
+    T: ?Sized,
+{
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T> Send for InitStateLock<T> where T: ?Sized + Send {}
+unsafe impl<T> Sync for InitStateLock<T> where T: ?Sized + Send {}
+
+impl<T> InitStateLock<T> {
+    /// Create an instance
+    pub const fn new(value: T) -> Self {
+        Self {
+            data: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T> interface::ReadWriteEx for InitStateLock<T> {
+    type Data = T;
+
+    fn write<R>(&self, func: impl FnOnce(&mut Self::Data) -> R) -> R {
+        assert!(
+            state::state_manager().is_init(),
+            "InitStateLock::write called after init"
+        );
+        // an IRQ handler reading it mid-write would see a torn value
+        assert!(
+            exception::asynchronous::is_local_irq_masked(),
+            "InitStateLock::write called with IRQs unmasked"
+        );
+
+        let data = unsafe { &mut *self.data.get() };
+        func(data)
+    }
+
+    fn read<R>(&self, func: impl FnOnce(&Self::Data) -> R) -> R {
+        let data = unsafe { &*self.data.get() };
+        func(data)
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * File: ring_buffer.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 4:12:37 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Lock-free single producer, single consumer byte ring buffer.
+//!
+//! Meant for an IRQ handler (the producer) handing bytes to thread code (the consumer) on the same
+//! core, without either side taking a lock. Each index is only ever written by one side, so plain
+//! atomic loads and stores do: no read-modify-write atomics, which need exclusive accesses that
+//! don't work with the MMU (and so the caches) off.
+
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Ring buffer of `SIZE` bytes, of which `SIZE - 1` can be used. `SIZE` must be a power of two.
+pub struct RingBuffer<const SIZE: usize> {
+    data: UnsafeCell<[u8; SIZE]>,
+
+    /// Next slot to write, only written by the producer
+    head: AtomicUsize,
+
+    /// Next slot to read, only written by the consumer
+    tail: AtomicUsize,
+}
+
+unsafe impl<const SIZE: usize> Sync for RingBuffer<SIZE> {}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl<const SIZE: usize> RingBuffer<SIZE> {
+    const MASK: usize = SIZE - 1;
+
+    /// Create an empty instance
+    pub const fn new() -> Self {
+        assert!(SIZE.is_power_of_two());
+
+        Self {
+            data: UnsafeCell::new([0; SIZE]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Append a byte. Returns false (and drops the byte) if the buffer is full.
+    ///
+    /// # Safety
+    ///
+    /// - Only one producer at a time, e.g. only the IRQ handler, or thread code with the IRQ
+    ///   masked.
+    pub unsafe fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) & Self::MASK;
+
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+
+        (*self.data.get())[head] = byte;
+        // publish the byte before the consumer can see the new head
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Take the oldest byte, if there is one.
+    ///
+    /// # Safety
+    ///
+    /// - Only one consumer at a time.
+    pub unsafe fn pop(&self) -> Option<u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let byte = (*self.data.get())[tail];
+        // hand the slot back to the producer only after the byte is out
+        self.tail.store((tail + 1) & Self::MASK, Ordering::Release);
+        Some(byte)
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * File: spin_lock.rs
+ * Project: RpiOS
+ * File Created: Tuesday, 20th October 2026 9:05:48 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Ticket spinlocks, for state shared between cores.
+//!
+//! A lock hands out tickets in order and serves them in order, so cores get the lock in the order
+//! they asked for it and none starves. Taking a ticket is an atomic read-modify-write: LDAXR/STLXR,
+//! or a single LSE instruction when built for a core that has them. Both need the MMU and caches
+//! on: exclusives to non-cacheable memory aren't guaranteed to ever succeed on the BCM SoCs, since
+//! there is no global exclusive monitor. The acquire/release orderings order the data accesses
+//! inside the lock against the lock itself.
+//!
+//! The locks aren't reentrant: locking one again from inside its own closure spins forever.
+
+use super::interface;
+use crate::{cpu, exception};
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A ticket spinlock.
+///
+/// Doesn't mask IRQs: don't use it for state an IRQ handler locks too, the handler would spin
+/// forever on a lock its own core holds. See [`IRQSafeSpinLock`].
+pub struct SpinLock<T>
+where
+    T: ?Sized,
+{
+    /// Next ticket to hand out
+    next_ticket: AtomicUsize,
+
+    /// Ticket whose holder may enter
+    now_serving: AtomicUsize,
+
+    data: UnsafeCell<T>,
+}
+
+/// A ticket spinlock masking IRQs on the executing core while it's held, so it can protect state
+/// shared with IRQ handlers too
+pub struct IRQSafeSpinLock<T>
+where
+    T: ?Sized,
+{
+    inner: SpinLock<T>,
+}
+
+unsafe impl<T> Send for SpinLock<T> where T: ?Sized + Send {}
+unsafe impl<T> Sync for SpinLock<T> where T: ?Sized + Send {}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl<T> SpinLock<T> {
+    /// Create an unlocked instance
+    pub const fn new(value: T) -> Self {
+        Self {
+            next_ticket: AtomicUsize::new(0),
+            now_serving: AtomicUsize::new(0),
+            data: UnsafeCell::new(value),
+        }
+    }
+}
+
+impl<T> IRQSafeSpinLock<T> {
+    /// Create an unlocked instance
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: SpinLock::new(value),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+// OS Interface Code
+//------------------------------------------------------------------------------
+
+impl<T> interface::Mutex for SpinLock<T> {
+    type Data = T;
+
+    fn lock<R, F>(&self, func: F) -> R
+    where
+        F: FnOnce(&mut Self::Data) -> R,
+    {
+        // wraps around, like the ticket being served
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+
+        // Acquire: see everything the previous holder wrote
+        while self.now_serving.load(Ordering::Acquire) != ticket {
+            cpu::nop();
+        }
+
+        let data = unsafe { &mut *self.data.get() };
+        let ret = func(data);
+
+        // Release: the next holder sees everything written here. Only the holder writes
+        // now_serving, a plain store does.
+        self.now_serving.store(ticket.wrapping_add(1), Ordering::Release);
+
+        ret
+    }
+}
+
+impl<T> interface::Mutex for IRQSafeSpinLock<T> {
+    type Data = T;
+
+    fn lock<R, F>(&self, func: F) -> R
+    where
+        F: FnOnce(&mut Self::Data) -> R,
+    {
+        // masked before taking a ticket, an IRQ handler locking it too would wait for us forever
+        exception::asynchronous::exec_with_irq_masked(|| self.inner.lock(func))
+    }
+}
@@ -0,0 +1,81 @@
+/*
+ * File: time.rs
+ * Project: RpiOS
+ * File Created: Monday, 19th October 2026 8:05:47 pm
+ * Author: Elad Matia (elad.matia@gmail.com)
+ */
+
+//! Timer primitives.
+
+#[cfg(target_arch = "aarch64")]
+#[path = "_arch/aarch64/time.rs"]
+mod arch_time;
+
+use core::time::Duration;
+
+pub use arch_time::time_manager;
+
+//--------------------------------------------------------------------------------------------------
+// Public Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Timekeeping interfaces.
+pub mod interface {
+    use core::time::Duration;
+
+    /// Time management functions.
+    pub trait TimeManager {
+        /// The timer's resolution.
+        fn resolution(&self) -> Duration;
+
+        /// The uptime since power-on of the device.
+        ///
+        /// This includes time consumed by firmware and bootloaders.
+        fn uptime(&self) -> Duration;
+
+        /// Spin for a given duration.
+        fn spin_for(&self, duration: Duration);
+    }
+}
+
+/// A point in time after which a wait has timed out
+#[derive(Copy, Clone)]
+pub struct Deadline {
+    /// Uptime at which the deadline passes, `None` for never (a timeout too long to count)
+    end: Option<Duration>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Public Code
+//--------------------------------------------------------------------------------------------------
+
+impl Deadline {
+    /// A deadline `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        use interface::TimeManager;
+
+        Self {
+            end: time_manager().uptime().checked_add(timeout),
+        }
+    }
+
+    /// True once the deadline has passed
+    pub fn has_passed(&self) -> bool {
+        use interface::TimeManager;
+
+        match self.end {
+            Some(end) => time_manager().uptime() >= end,
+            None => false,
+        }
+    }
+
+    /// Time left until the deadline, zero once it has passed
+    pub fn remaining(&self) -> Duration {
+        use interface::TimeManager;
+
+        match self.end {
+            Some(end) => end.saturating_sub(time_manager().uptime()),
+            None => Duration::MAX,
+        }
+    }
+}